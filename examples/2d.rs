@@ -2,7 +2,9 @@ use doppler::assets_cache::AssetsCache;
 use doppler::client::Client;
 use doppler::components::*;
 use doppler::glutin::event::{ElementState, VirtualKeyCode};
+use doppler::math::{ortho, vec3, Matrix4, Point3};
 use doppler::shader::Shader;
+use doppler::uniform_buffer::SharedUniforms;
 
 pub struct Client2D {
     delta: f32,
@@ -39,15 +41,17 @@ impl Client for Client2D {
         self.models_2d.model = cache.load_2d("resources/objects/ddd.jpg");
     }
 
+    unsafe fn update_uniforms(&mut self, uniforms: &mut SharedUniforms) {
+        // looking down at the plane, -Z is up on the screen
+        let eye = Point3::new(0.0, 1.0, 0.0);
+        let view = Matrix4::look_at_rh(eye, Point3::new(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+        let projection = ortho(-1.0, 1.0, -1.0, 1.0, 0.1, 10.0);
+        uniforms.update_camera(&projection, &view, vec3(0.0, 1.0, 0.0));
+    }
+
     unsafe fn draw(&mut self) {
-        // use doppler::math::prelude::*;
+        self.shader.use_program();
         self.models_2d.draw(&self.shader);
-        // let projection: Matrix4<f32> = perspective(
-        //     Deg(self.camera.zoom),
-        //     consts::SCR_WIDTH as f32 / consts::SCR_HEIGHT as f32,
-        //     0.1,
-        //     1000.0,
-        // );
     }
 
     fn update(&mut self, delta: f32) {
@@ -2,7 +2,9 @@ use doppler::assets_cache::AssetsCache;
 use doppler::camera::*;
 use doppler::client::Client;
 use doppler::glutin::event::{ElementState, VirtualKeyCode};
+use doppler::log::warn;
 use doppler::map::*;
+use doppler::uniform_buffer::SharedUniforms;
 
 pub struct ExampleClient {
     map: Map,
@@ -46,10 +48,17 @@ impl Client for ExampleClient {
     }
 
     fn load_assets(&mut self, cache: &mut AssetsCache) {
-        cache.load_all_from_file("resources/test_objects.txt");
+        // entries that could not be loaded are listed in the error, the rest is usable
+        if let Err(e) = cache.load_manifest("resources/assets.yaml") {
+            warn!("{}", e);
+        }
         self.map = MapSave::load("resources/test_map.yaml", cache);
     }
 
+    unsafe fn update_uniforms(&mut self, uniforms: &mut SharedUniforms) {
+        self.map.update_uniforms(uniforms);
+    }
+
     unsafe fn draw(&mut self) {
         self.map.draw();
     }
@@ -0,0 +1,6 @@
+// per frame camera data, updated by `SharedUniforms::update_camera`
+layout (std140) uniform Camera {
+    mat4 projection;
+    mat4 view;
+    vec3 viewPos;
+};
@@ -2,6 +2,7 @@
 layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec3 aNormal;
 layout (location = 2) in vec2 aTexCoords;
+layout (location = 5) in vec4 aColor;
 
 #define PRECISION 0.01
 #define MIN_DIST 20.0
@@ -9,17 +10,18 @@ layout (location = 2) in vec2 aTexCoords;
 out vec3 FragPos;
 out vec3 Normal;
 out vec2 TexCoords;
+out vec4 VertexColor;
+
+#include "common/camera.glsl"
 
 uniform mat4 model;
-uniform mat4 view;
-uniform vec3 viewPos;
-uniform mat4 projection;
 
 void main()
 {
     FragPos = vec3(model * vec4(aPos, 1.0));
     Normal = mat3(transpose(inverse(model))) * aNormal;
     TexCoords = aTexCoords;
+    VertexColor = aColor;
 
 
     gl_Position = projection * view * vec4(FragPos, 1.0);
@@ -1,20 +1,174 @@
+use crate::consts;
+use crate::gpu::TextureHandle;
+use crate::loader::{AsyncLoader, LoadProgress};
+use crate::manifest::{
+    parent_dir, resolve_texture_path, AssetManifest, LodEntry, ManifestError, ModelEntry,
+};
 use crate::mesh::Texture;
-use crate::model::Model;
-use crate::utils::load_texture_from_dir;
-use log::{error, info};
+use crate::model::{Model, TextureRef};
+use crate::shader::{Shader, ShaderBuilder};
+use crate::utils::{self, load_cubemap, load_texture_from_fullpath, ImageData};
+use log::{error, info, warn};
+use std::cell::{Ref, RefCell, RefMut};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Shared reference to an asset loaded by the `AssetsCache`, clones are cheap.
+/// Assets nobody holds a handle to anymore are freed by `AssetsCache::unload_unused`.
+/// Hot reloading replaces the asset behind all clones (see `AssetsCache::poll_changes`).
+pub struct Handle<T>(Rc<RefCell<T>>);
+
+impl<T> Handle<T> {
+    pub fn new(asset: T) -> Self {
+        Handle(Rc::new(RefCell::new(asset)))
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.0.borrow()
+    }
+
+    /// Mutable access to the shared asset, changes are seen by every clone.
+    /// Panics while the asset is borrowed elsewhere, like `RefCell::borrow_mut`.
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.0.borrow_mut()
+    }
+
+    /// Replaces the asset for every clone of the handle, returns the previous one.
+    pub fn replace(&self, asset: T) -> T {
+        self.0.replace(asset)
+    }
+
+    /// Number of handles to the asset, including the one kept by the cache.
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+
+    pub fn ptr_eq(&self, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: Clone> Handle<T> {
+    /// Mutable access to the asset, it is copied first when other handles share it.
+    /// The copy is private to this handle: it is not the cached asset anymore, so hot
+    /// reloading doesn't update it. Use `borrow_mut` to change the asset for everyone.
+    pub fn make_mut(&mut self) -> &mut T {
+        if Rc::strong_count(&self.0) > 1 {
+            let copy = self.0.borrow().clone();
+            self.0 = Rc::new(RefCell::new(copy));
+        }
+        Rc::get_mut(&mut self.0)
+            .expect("handle was copied")
+            .get_mut()
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle(Rc::clone(&self.0))
+    }
+}
+
+impl<T: Default> Default for Handle<T> {
+    fn default() -> Self {
+        Handle::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.borrow().fmt(f)
+    }
+}
 
 #[derive(Default)]
 pub struct AssetsCache {
-    textures: HashMap<u64, Texture>,
-    models: HashMap<u64, Model>,
+    textures: HashMap<String, Handle<Texture>>,
+    models: HashMap<ModelKey, Handle<Model>>,
+    shaders: HashMap<String, Shader>,
+    cubemaps: HashMap<String, TextureHandle>,
+    lods: HashMap<String, Vec<LodEntry>>,
+    tags: HashMap<String, Vec<String>>,
+    /// model paths and textures listed by objects lists and manifests, loaded or not,
+    /// used to resolve legacy map hashes
+    known_models: Vec<(String, Option<String>)>,
+    /// model files under `consts::RESOURCES_DIR` by legacy hash, found on first use
+    legacy_files: RefCell<Option<HashMap<u64, String>>>,
+    loader: AsyncLoader,
+    /// source files of cached models and textures, checked by `poll_changes`
+    watched_models: HashMap<ModelKey, WatchedModel>,
+    watched_textures: HashMap<String, WatchedFile>,
+    last_poll: Option<Instant>,
+}
+
+/// Model path and diffuse texture override, a model is cached once per override.
+type ModelKey = (String, Option<String>);
+
+fn model_key(path: &str, diff_texture: Option<&str>) -> ModelKey {
+    (path.into(), diff_texture.map(String::from))
+}
+
+struct WatchedFile {
+    path: String,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    fn new(path: &str) -> Self {
+        WatchedFile {
+            path: path.into(),
+            modified: utils::modified(Path::new(path)),
+        }
+    }
+
+    /// True once for every modification of the file.
+    fn changed(&mut self) -> bool {
+        let modified = utils::modified(Path::new(&self.path));
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+/// Everything needed to load the model again.
+struct WatchedModel {
+    file: WatchedFile,
+    diff_texture: Option<String>,
+    /// texture type and cache key of textures replaced by a manifest
+    materials: Vec<(String, String)>,
+}
+
+impl WatchedModel {
+    fn new(path: &str, diff_texture: Option<&str>) -> Self {
+        WatchedModel {
+            file: WatchedFile::new(path),
+            diff_texture: diff_texture.map(String::from),
+            materials: Vec::new(),
+        }
+    }
+}
+
+/// Replaces textures of the same type in every mesh of the model.
+fn set_material(model: &mut Model, texture: &Texture) {
+    for mesh in &mut model.meshes {
+        mesh.textures.retain(|t| t.type_ != texture.type_);
+        mesh.textures.push(texture.clone());
+    }
 }
 
 impl AssetsCache {
-    pub fn load_2d(&mut self, path: &str) -> Model {
+    /// Textured plane showing the image at `path`, cached under the image path.
+    pub fn load_2d(&mut self, path: &str) -> Handle<Model> {
+        if let Some(model) = self.models.get(&model_key(path, None)) {
+            return model.clone();
+        }
         let fullpath = Path::new(&path);
         let dir = fullpath.parent().unwrap().to_str().unwrap().to_string();
         let filename = fullpath.file_name().unwrap().to_str().unwrap();
@@ -23,48 +177,202 @@ impl AssetsCache {
         for ele in &mut model.meshes {
             ele.textures.push(texture.clone())
         }
+        let model = Handle::new(model);
+        self.models.insert(model_key(path, None), model.clone());
         model
     }
 
     pub fn load_all_from_file(&mut self, path: &str) {
-        let path = std::path::Path::new(path);
-        let meta = std::fs::metadata(path);
-        if meta.is_err() {
-            error!("There is no assets file");
-            return;
+        for (model, texture) in read_objects_list(path) {
+            self.remember_model(&model, texture.as_deref());
+            if self.has_model_ext(&model, texture.as_deref()) {
+                error!("Skip duplicated model: {}", model);
+                continue;
+            }
+            self.load_model_ext(&model, texture.as_deref());
+            info!("Added model from path {}", model);
         }
-        use std::fs;
-        use std::io::prelude::*;
-        use std::io::BufReader;
-        let buf = BufReader::new(fs::File::open(path).expect("no such file"));
-        let lines: Vec<String> = buf
-            .lines()
-            .map(|l| l.expect("Could not parse line"))
-            .collect();
+    }
 
-        for line in lines {
-            let mut splitted = line.split_whitespace();
-            let path = splitted.next();
-            if path.is_none() || self.has_model(path.unwrap()) {
-                error!("Skip wrong line: {}", line);
-                continue;
+    /// Same as `load_all_from_file`, models are loaded in the background.
+    pub fn load_all_from_file_async(&mut self, path: &str) {
+        for (model, texture) in read_objects_list(path) {
+            self.remember_model(&model, texture.as_deref());
+            self.load_model_async(&model, texture.as_deref());
+        }
+    }
+
+    /// Queues the model for loading on worker threads, it becomes available through
+    /// `get_model_ext` once `load_progress` reports it as done.
+    pub fn load_model_async(&mut self, path: &str, diff_texture: Option<&str>) {
+        if !self.has_model_ext(path, diff_texture) {
+            self.loader.queue_model(path, diff_texture);
+        }
+    }
+
+    pub fn load_progress(&self) -> LoadProgress {
+        self.loader.progress()
+    }
+
+    /// Uploads assets decoded in the background, spending at most about `budget` on it.
+    /// Has to be called on the render thread, the engine does it once per frame.
+    pub fn process_uploads(&mut self, budget: Duration) -> LoadProgress {
+        let mut loader = std::mem::take(&mut self.loader);
+        let progress = loader.upload(self, Some(budget));
+        self.loader = loader;
+        progress
+    }
+
+    /// Blocks until every asset queued for background loading is uploaded.
+    pub fn finish_loading(&mut self) -> LoadProgress {
+        let mut loader = std::mem::take(&mut self.loader);
+        let progress = loader.finish(self);
+        self.loader = loader;
+        progress
+    }
+
+    /// Loads all valid entries from an asset manifest, see `manifest` module for the format.
+    /// Invalid entries are skipped and reported together in the returned error.
+    pub fn load_manifest(&mut self, path: &str) -> Result<(), ManifestError> {
+        info!("Loading assets manifest: {}", path);
+        let (manifest, issues) = AssetManifest::load(path);
+        for model in &manifest.models {
+            self.load_manifest_model(model);
+        }
+        for texture in &manifest.textures {
+            let file_name = Path::new(&texture.path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            self.get_material_texture(&parent_dir(&texture.path), file_name, &texture.kind);
+            self.add_tags(&texture.path, &texture.tags);
+        }
+        for cubemap in &manifest.cubemaps {
+            let faces: Vec<&str> = cubemap.faces.iter().map(|face| face.as_str()).collect();
+            let texture = TextureHandle::from_raw(unsafe { load_cubemap(&faces) });
+            self.cubemaps.insert(cubemap.name.clone(), texture);
+        }
+        for shader in &manifest.shaders {
+            let mut builder = ShaderBuilder::new()
+                .vertex_file(&shader.vertex)
+                .fragment_file(&shader.fragment);
+            if let Some(geometry) = &shader.geometry {
+                builder = builder.geometry_file(geometry);
+            }
+            let program = builder.build_or_placeholder();
+            self.shaders.insert(shader.name.clone(), program);
+        }
+
+        if issues.is_empty() {
+            info!("Assets manifest loaded: {}", path);
+            return Ok(());
+        }
+        let e = ManifestError {
+            path: path.into(),
+            issues,
+        };
+        error!("{}", e);
+        Err(e)
+    }
+
+    fn load_manifest_model(&mut self, entry: &ModelEntry) {
+        self.remember_model(&entry.path, entry.texture.as_deref());
+        let key = model_key(&entry.path, entry.texture.as_deref());
+        if !self.models.contains_key(&key) {
+            self.load_model_ext(&entry.path, entry.texture.as_deref());
+        }
+        let model = self.models.get(&key).cloned();
+        if let Some(model) = model.filter(|_| !entry.materials.is_empty()) {
+            let dir = parent_dir(&entry.path);
+            for (type_name, texture_path) in &entry.materials {
+                let texture_path = resolve_texture_path(&dir, texture_path);
+                let file_name = Path::new(&texture_path)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default();
+                let mut texture =
+                    self.get_material_texture(&parent_dir(&texture_path), file_name, type_name);
+                texture.type_ = type_name.clone();
+                // through the shared handle, components already using the model see it too
+                set_material(&mut model.borrow_mut(), &texture);
+                // a reload builds a new model, `reload_model` applies these again
+                if let Some(watched) = self.watched_models.get_mut(&key) {
+                    watched
+                        .materials
+                        .push((type_name.clone(), file_name.into()));
+                }
+            }
+        }
+        for lod in &entry.lods {
+            if !self.has_model_ext(&lod.path, entry.texture.as_deref()) {
+                self.load_model_ext(&lod.path, entry.texture.as_deref());
+            }
+        }
+        if !entry.lods.is_empty() {
+            self.lods.insert(entry.path.clone(), entry.lods.clone());
+        }
+        self.add_tags(&entry.path, &entry.tags);
+    }
+
+    fn remember_model(&mut self, path: &str, texture: Option<&str>) {
+        if !self.known_models.iter().any(|(known, _)| known == path) {
+            self.known_models
+                .push((path.into(), texture.map(String::from)));
+        }
+    }
+
+    fn add_tags(&mut self, path: &str, tags: &[String]) {
+        for tag in tags {
+            let paths = self.tags.entry(tag.clone()).or_default();
+            if !paths.iter().any(|p| p == path) {
+                paths.push(path.into());
             }
-            let texture = splitted.next();
-            self.load_model_ext(path.unwrap(), texture);
-            info!("Added model from path {}", path.unwrap());
         }
     }
 
+    /// Paths of models and textures tagged in a manifest.
+    pub fn tagged(&self, tag: &str) -> &[String] {
+        self.tags.get(tag).map_or(&[], |paths| paths.as_slice())
+    }
+
+    /// Lower detail variants of a model declared in a manifest.
+    pub fn get_lods(&self, path: &str) -> &[LodEntry] {
+        self.lods.get(path).map_or(&[], |lods| lods.as_slice())
+    }
+
+    pub fn get_shader(&self, name: &str) -> Option<&Shader> {
+        self.shaders.get(name)
+    }
+
+    pub fn get_cubemap(&self, name: &str) -> Option<u32> {
+        self.cubemaps.get(name).map(TextureHandle::id)
+    }
+
+    /// Registers a model built in code, e.g. from `mesh::primitives`, under `path`. It is
+    /// not watched for changes. A model already cached there keeps being used.
+    pub fn add_model(&mut self, path: &str, model: Model) -> Handle<Model> {
+        self.models
+            .entry(model_key(path, None))
+            .or_insert_with(|| Handle::new(model))
+            .clone()
+    }
+
+    /// True when the model is cached with any diffuse texture override.
     pub fn has_model(&self, path: &str) -> bool {
-        self.models.contains_key(&Self::path_hash(path))
+        self.models.keys().any(|(cached, _)| cached == path)
     }
 
-    pub fn get_model(&mut self, path: &str) -> Model {
+    pub fn has_model_ext(&self, path: &str, diff_texture: Option<&str>) -> bool {
+        self.models.contains_key(&model_key(path, diff_texture))
+    }
+
+    pub fn get_model(&mut self, path: &str) -> Handle<Model> {
         self.get_model_ext(path, None)
     }
 
-    pub fn get_model_ext(&mut self, path: &str, diff_texture: Option<&str>) -> Model {
-        match self.models.get(&Self::path_hash(path)) {
+    /// Loads the model on first use, later calls with the same diffuse texture share it.
+    pub fn get_model_ext(&mut self, path: &str, diff_texture: Option<&str>) -> Handle<Model> {
+        match self.models.get(&model_key(path, diff_texture)) {
             Some(model) => model.clone(),
             None => {
                 self.load_model_ext(path, diff_texture);
@@ -73,36 +381,310 @@ impl AssetsCache {
         }
     }
 
-    pub fn get_model_by_hash(&mut self, hash: &u64) -> Option<Model> {
-        self.models.get(hash).cloned()
+    /// The model cached without a diffuse texture override.
+    pub fn get_model_by_path(&self, path: &str) -> Option<Handle<Model>> {
+        self.models.get(&model_key(path, None)).cloned()
+    }
+
+    pub fn get_texture_by_path(&self, path: &str) -> Option<Handle<Texture>> {
+        self.textures.get(path).cloned()
+    }
+
+    /// Model paths (with the diffuse texture they were listed with) by the hash that maps
+    /// used to store instead of paths. Candidates are the models listed by objects lists
+    /// and manifests, loaded models and `consts::LEGACY_OBJECTS_LIST`, earlier ones win.
+    /// Hashes missing here can still match a file, see `legacy_model_file`.
+    pub fn legacy_models(&self) -> HashMap<u64, (String, Option<String>)> {
+        let mut candidates = self.known_models.clone();
+        candidates.extend(self.models.keys().cloned());
+        if utils::path_exists(Path::new(consts::LEGACY_OBJECTS_LIST)) {
+            candidates.extend(read_objects_list(consts::LEGACY_OBJECTS_LIST));
+        }
+
+        let mut models = HashMap::new();
+        for (path, texture) in candidates {
+            models
+                .entry(Self::legacy_path_hash(&path))
+                .or_insert((path, texture));
+        }
+        models
+    }
+
+    /// Model file under `consts::RESOURCES_DIR` with the legacy hash, for objects whose
+    /// model is in no list. The directory is only walked the first time.
+    pub fn legacy_model_file(&self, hash: u64) -> Option<String> {
+        let mut legacy_files = self.legacy_files.borrow_mut();
+        let files = legacy_files.get_or_insert_with(|| {
+            let mut files = Vec::new();
+            find_model_files(Path::new(consts::RESOURCES_DIR), &mut files);
+            let mut by_hash = HashMap::new();
+            for path in files {
+                by_hash.entry(Self::legacy_path_hash(&path)).or_insert(path);
+            }
+            by_hash
+        });
+        files.get(&hash).cloned()
     }
 
     fn load_model_ext(&mut self, path: &str, diff_texture: Option<&str>) {
-        let hash = Self::path_hash(path);
-        info!("Loading model: {}({})", path, hash);
+        info!("Loading model: {}", path);
         let model = Model::new_ext(path, diff_texture, self, false);
-        self.models.insert(hash, model);
+        self.insert_model(path, diff_texture, model);
     }
 
-    pub fn get_material_texture(&mut self, dir: &str, path: &str, type_name: &str) -> Texture {
-        match self.textures.get(&Self::path_hash(path)) {
+    /// Loads the texture on first use, later calls share it.
+    pub fn get_texture(&mut self, dir: &str, path: &str, type_name: &str) -> Handle<Texture> {
+        match self.textures.get(path) {
             Some(texture) => texture.clone(),
             None => {
-                let directory: String = dir.into();
+                let fullpath = format!("{}/{}", dir, path);
                 let texture = Texture {
-                    id: unsafe { load_texture_from_dir(path, &directory) },
+                    handle: TextureHandle::from_raw(unsafe {
+                        load_texture_from_fullpath(&fullpath)
+                    }),
                     type_: type_name.into(),
                     path: path.into(),
                 };
-                self.textures.insert(Self::path_hash(path), texture.clone());
-                texture
+                self.insert_texture(texture, &fullpath)
+            }
+        }
+    }
+
+    /// Texture for a mesh material, the GL texture is shared with the cache.
+    pub fn get_material_texture(&mut self, dir: &str, path: &str, type_name: &str) -> Texture {
+        self.get_texture(dir, path, type_name).borrow().clone()
+    }
+
+    /// Frees models and textures that are only referenced by the cache, e.g. after
+    /// switching levels. Returns the number of unloaded assets.
+    pub fn unload_unused(&mut self) -> usize {
+        let models = self.models.len();
+        self.models.retain(|_, model| model.ref_count() > 1);
+        // meshes of the dropped models released their textures
+        let textures = self.textures.len();
+        self.textures.retain(|_, texture| {
+            texture.ref_count() > 1 || texture.borrow().handle.ref_count() > 1
+        });
+
+        let (loaded_models, loaded_textures) = (&self.models, &self.textures);
+        self.watched_models
+            .retain(|path, _| loaded_models.contains_key(path));
+        self.watched_textures
+            .retain(|path, _| loaded_textures.contains_key(path));
+
+        let unloaded = models - self.models.len() + textures - self.textures.len();
+        if unloaded > 0 {
+            info!(
+                "Unloaded {} models and {} textures",
+                models - self.models.len(),
+                textures - self.textures.len()
+            );
+        }
+        unloaded
+    }
+
+    pub(crate) fn has_texture(&self, path: &str) -> bool {
+        self.textures.contains_key(path)
+    }
+
+    /// Caches the texture under its file name, `fullpath` is watched for changes.
+    pub(crate) fn insert_texture(&mut self, texture: Texture, fullpath: &str) -> Handle<Texture> {
+        let key = texture.path.clone();
+        let texture = Handle::new(texture);
+        self.textures.insert(key.clone(), texture.clone());
+        self.watched_textures
+            .insert(key, WatchedFile::new(fullpath));
+        texture
+    }
+
+    /// Caches an image decoded from a model file. An already cached texture gets the new
+    /// image instead, e.g. when the model is hot reloaded.
+    pub(crate) fn insert_embedded_texture(&mut self, texture: &TextureRef, image: &ImageData) {
+        if let Some(cached) = self.textures.get(&texture.file) {
+            unsafe { utils::upload_texture_to(cached.borrow().id(), image) };
+            return;
+        }
+        let id = unsafe { utils::upload_texture(image) };
+        self.textures.insert(
+            texture.file.clone(),
+            Handle::new(Texture {
+                handle: TextureHandle::from_raw(id),
+                type_: texture.type_.clone(),
+                path: texture.file.clone(),
+            }),
+        );
+    }
+
+    pub(crate) fn insert_model(&mut self, path: &str, diff_texture: Option<&str>, model: Model) {
+        let key = model_key(path, diff_texture);
+        self.models.insert(key.clone(), Handle::new(model));
+        self.watched_models
+            .insert(key, WatchedModel::new(path, diff_texture));
+    }
+
+    /// Reloads models and textures whose files changed on disk. The new data replaces the
+    /// old one behind existing handles, so every component using them is updated. Files are
+    /// checked at most every `consts::ASSET_POLL_INTERVAL` ms, returns the number of reloaded
+    /// assets.
+    pub fn poll_changes(&mut self) -> usize {
+        let now = Instant::now();
+        let interval = Duration::from_millis(consts::ASSET_POLL_INTERVAL);
+        if matches!(self.last_poll, Some(last) if now.duration_since(last) < interval) {
+            return 0;
+        }
+        self.last_poll = Some(now);
+
+        let textures: Vec<String> = self
+            .watched_textures
+            .iter_mut()
+            .filter_map(|(key, file)| file.changed().then(|| key.clone()))
+            .collect();
+        let models: Vec<ModelKey> = self
+            .watched_models
+            .iter_mut()
+            .filter_map(|(key, watched)| watched.file.changed().then(|| key.clone()))
+            .collect();
+
+        // textures first, so reloaded models don't need to wait for the next poll
+        let textures = textures
+            .iter()
+            .filter(|key| self.reload_texture(key))
+            .count();
+        let models = models.iter().filter(|key| self.reload_model(key)).count();
+
+        textures + models
+    }
+
+    /// Uploads the new image into the existing GL texture, the previous image is kept
+    /// when the file cannot be decoded.
+    fn reload_texture(&mut self, key: &str) -> bool {
+        let (texture, file) = match (self.textures.get(key), self.watched_textures.get(key)) {
+            (Some(texture), Some(file)) => (texture, file),
+            _ => return false,
+        };
+        match utils::decode_image(&file.path, utils::image_format(&file.path)) {
+            Ok(image) => {
+                unsafe { utils::upload_texture_to(texture.borrow().id(), &image) };
+                info!("Reloaded texture {}", file.path);
+                true
+            }
+            Err(e) => {
+                warn!("Keeping previous texture, {}", e);
+                false
+            }
+        }
+    }
+
+    /// Loads the model again, the previous one is kept when that fails.
+    fn reload_model(&mut self, key: &ModelKey) -> bool {
+        let (path, diff_texture, materials) = match self.watched_models.get(key) {
+            Some(watched) => (
+                watched.file.path.clone(),
+                watched.diff_texture.clone(),
+                watched.materials.clone(),
+            ),
+            None => return false,
+        };
+        let mut model = match Model::try_new_ext(&path, diff_texture.as_deref(), self, false) {
+            Ok(model) => model,
+            Err(e) => {
+                warn!("Keeping previous model, {}", e);
+                return false;
+            }
+        };
+        for (type_name, key) in &materials {
+            if let Some(texture) = self.textures.get(key) {
+                let mut texture = texture.borrow().clone();
+                texture.type_ = type_name.clone();
+                set_material(&mut model, &texture);
+            }
+        }
+
+        match self.models.get(key) {
+            Some(handle) => {
+                // drops the GL buffers of the previous model
+                handle.replace(model);
+                info!("Reloaded model {}", path);
+                true
             }
+            None => false,
         }
     }
 
-    pub fn path_hash(path: &str) -> u64 {
+    pub fn loaded_models(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn loaded_textures(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Hash of the path stored by old map files. `DefaultHasher` output is not
+    /// guaranteed to be stable across Rust releases, so it is only used to migrate them.
+    pub fn legacy_path_hash(path: &str) -> u64 {
         let mut hasher = DefaultHasher::new();
         path.hash(&mut hasher);
         hasher.finish()
     }
 }
+
+/// OBJ and glTF files in `dir` and its subdirectories, sorted.
+fn find_model_files(dir: &Path, files: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            find_model_files(&path, files);
+            continue;
+        }
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        if matches!(extension.as_str(), "obj" | "gltf" | "glb") {
+            if let Some(path) = path.to_str() {
+                files.push(path.into());
+            }
+        }
+    }
+}
+
+/// Model paths with optional diffuse texture, one per line: `path [texture]`.
+fn read_objects_list(path: &str) -> Vec<(String, Option<String>)> {
+    use std::fs;
+    use std::io::prelude::*;
+    use std::io::BufReader;
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            error!("There is no assets file {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+
+    let mut objects = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Cannot read line {} of {}: {}", i + 1, path, e);
+                continue;
+            }
+        };
+        let mut splitted = line.split_whitespace();
+        match splitted.next() {
+            Some(model) => objects.push((model.to_string(), splitted.next().map(String::from))),
+            None => error!("Skip wrong line: {}", line),
+        }
+    }
+
+    objects
+}
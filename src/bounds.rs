@@ -0,0 +1,247 @@
+//! Bounding volumes for culling, picking, camera framing and collision.
+//!
+//! `Mesh` and `Model` cache their bounds in model space when created,
+//! `ModelComponent::bounds` moves them to world space.
+use cgmath::prelude::*;
+use cgmath::{vec3, Matrix4, Vector3};
+
+/// Axis-aligned bounding box, empty while `min` is greater than `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::empty()
+    }
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn empty() -> Self {
+        Aabb {
+            min: vec3(f32::MAX, f32::MAX, f32::MAX),
+            max: vec3(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn from_points<I: IntoIterator<Item = Vector3<f32>>>(points: I) -> Self {
+        let mut aabb = Aabb::empty();
+        for point in points {
+            aabb.extend(point);
+        }
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn extend(&mut self, point: Vector3<f32>) {
+        self.min = vec3(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = vec3(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut aabb = *self;
+        if !other.is_empty() {
+            aabb.extend(other.min);
+            aabb.extend(other.max);
+        }
+        aabb
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            vec3(min.x, min.y, min.z),
+            vec3(max.x, min.y, min.z),
+            vec3(min.x, max.y, min.z),
+            vec3(max.x, max.y, min.z),
+            vec3(min.x, min.y, max.z),
+            vec3(max.x, min.y, max.z),
+            vec3(min.x, max.y, max.z),
+            vec3(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Box around the transformed corners, rotations make it larger than the box around
+    /// the transformed contents.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        Aabb::from_points(
+            self.corners()
+                .iter()
+                .map(|corner| (matrix * corner.extend(1.0)).truncate()),
+        )
+    }
+
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+}
+
+/// Sphere containing the whole mesh, not necessarily the smallest one.
+/// Empty while `radius` is negative.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl Default for BoundingSphere {
+    fn default() -> Self {
+        BoundingSphere::empty()
+    }
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vector3<f32>, radius: f32) -> Self {
+        BoundingSphere { center, radius }
+    }
+
+    pub fn empty() -> Self {
+        BoundingSphere {
+            center: Vector3::zero(),
+            radius: -1.0,
+        }
+    }
+
+    /// Centered on the box around the points.
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        if points.is_empty() {
+            return BoundingSphere::empty();
+        }
+        let center = Aabb::from_points(points.iter().copied()).center();
+        let radius = points
+            .iter()
+            .map(|point| point.distance2(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+
+        BoundingSphere { center, radius }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.radius < 0.0
+    }
+
+    /// Smallest sphere containing both spheres.
+    pub fn union(&self, other: &BoundingSphere) -> BoundingSphere {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let offset = other.center - self.center;
+        let distance = offset.magnitude();
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+        let radius = (distance + self.radius + other.radius) * 0.5;
+
+        BoundingSphere {
+            center: self.center + offset * ((radius - self.radius) / distance),
+            radius,
+        }
+    }
+
+    /// The radius grows with the largest scale of the matrix.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingSphere {
+        if self.is_empty() {
+            return *self;
+        }
+        let scale = [matrix.x, matrix.y, matrix.z]
+            .iter()
+            .map(|axis| axis.truncate().magnitude())
+            .fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: (matrix * self.center.extend(1.0)).truncate(),
+            radius: self.radius * scale,
+        }
+    }
+
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        !self.is_empty() && point.distance2(self.center) <= self.radius * self.radius
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        let reach = self.radius + other.radius;
+        !self.is_empty()
+            && !other.is_empty()
+            && self.center.distance2(other.center) <= reach * reach
+    }
+}
+
+/// Box and sphere around the same geometry, empty for meshes without vertices.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        Bounds {
+            aabb: Aabb::from_points(points.iter().copied()),
+            sphere: BoundingSphere::from_points(points),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aabb.is_empty()
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            aabb: self.aabb.union(&other.aabb),
+            sphere: self.sphere.union(&other.sphere),
+        }
+    }
+
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Bounds {
+        Bounds {
+            aabb: self.aabb.transform(matrix),
+            sphere: self.sphere.transform(matrix),
+        }
+    }
+}
@@ -1,12 +1,20 @@
 use crate::assets_cache::AssetsCache;
+use crate::loader::LoadProgress;
+use crate::uniform_buffer::SharedUniforms;
 use glutin::event::{ElementState, VirtualKeyCode};
 
 pub trait Client {
     fn load_assets(&mut self, cache: &mut AssetsCache);
+    /// Called every frame while assets queued with `AssetsCache::load_model_async` are
+    /// uploaded, the last call reports `progress.is_done()`.
+    fn on_assets_progress(&mut self, _progress: &LoadProgress, _cache: &mut AssetsCache) {}
     fn update(&mut self, delta: f32);
     fn on_keyboard(&mut self, code: &VirtualKeyCode, state: &ElementState);
     fn on_mouse_scroll(&mut self, yoffset: f32);
     fn on_mouse_move(&mut self, x: f32, y: f32);
+    /// Called before every `draw` to fill the `Camera` and `Lights` blocks, e.g. with
+    /// `Map::update_uniforms`. The blocks keep their previous contents otherwise.
+    unsafe fn update_uniforms(&mut self, _uniforms: &mut SharedUniforms) {}
     unsafe fn draw(&mut self);
     #[cfg(feature = "imgui_inspect")]
     fn debug_draw(&mut self, ui: &imgui::Ui);
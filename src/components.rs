@@ -1,3 +1,5 @@
+use crate::assets_cache::Handle;
+use crate::bounds::Bounds;
 #[cfg(feature = "imgui_inspect")]
 use crate::imgui_helper::*;
 use crate::model::Model;
@@ -43,18 +45,26 @@ impl Transform {
 
 #[derive(Default)]
 pub struct ModelComponent {
-    pub model: Model,
-    pub hash: u64,
+    /// shared with the `AssetsCache` and every other component using the same model
+    pub model: Handle<Model>,
+    /// path the model was loaded from, used as its identifier in map files
+    pub path: String,
+    /// diffuse texture override passed to `AssetsCache::get_model_ext`
+    pub texture: Option<String>,
     pub transform: Transform,
 }
 
-
-
 impl ModelComponent {
     pub unsafe fn draw(&self, shader: &Shader) {
         let matrix = self.transform.get_matrix();
+        self.model.borrow().draw_at(shader, &matrix);
+    }
 
-        shader.set_mat4(c_str!("model"), &matrix);
-        self.model.Draw(shader);
+    /// World space bounds of the model placed by `transform`.
+    pub fn bounds(&self) -> Bounds {
+        self.model
+            .borrow()
+            .bounds
+            .transform(&self.transform.get_matrix())
     }
 }
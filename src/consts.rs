@@ -4,6 +4,20 @@
 pub const FRAMES_STORED: usize = 30;
 pub const SCR_WIDTH: u32 = 1280;
 pub const SCR_HEIGHT: u32 = 720;
+// fixed delta (in ms) passed to `Client::update` when running headless
+pub const HEADLESS_FRAME_TIME: f32 = 1000.0 / 60.0;
+// how often (in ms) shader sources are checked for changes when hot reload is enabled
+pub const SHADER_POLL_INTERVAL: u64 = 500;
+// how often (in ms) model and texture files are checked for changes when hot reload is enabled
+pub const ASSET_POLL_INTERVAL: u64 = 500;
+// worker threads parsing models and decoding images for `AssetsCache::load_model_async`
+pub const ASSET_LOADER_THREADS: usize = 4;
+// time (in ms) per frame spent uploading assets loaded in the background
+pub const ASSET_UPLOAD_BUDGET: u64 = 4;
+// objects list the engine used before maps stored model paths, see `AssetsCache::legacy_models`
+pub const LEGACY_OBJECTS_LIST: &str = "resources/test_objects.txt";
+// searched for model files when resolving legacy map hashes
+pub const RESOURCES_DIR: &str = "resources";
 
 pub const VERTEX_SHADER_SRC: &str = r#"
 #version 330 core
@@ -39,6 +53,36 @@ void main()
 }
 "#;
 
+// used in place of shaders that failed to load, draws everything in magenta
+pub const PLACEHOLDER_VERTEX_SRC: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+// same as resources/shaders/common/camera.glsl
+layout (std140) uniform Camera {
+    mat4 projection;
+    mat4 view;
+    vec3 viewPos;
+};
+
+uniform mat4 model;
+
+void main()
+{
+    gl_Position = projection * view * model * vec4(aPos, 1.0);
+}
+"#;
+
+pub const PLACEHOLDER_FRAGMENT_SRC: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+void main()
+{
+    FragColor = vec4(1.0, 0.0, 1.0, 1.0);
+}
+"#;
+
 pub const SH_FRAG_LAMP: &str = r#"
 #version 330 core
 out vec4 FragColor;
@@ -1,16 +1,21 @@
 use crate::assets_cache::AssetsCache;
 use crate::client::Client;
 use crate::consts;
-use crate::framebuffer::FramebufferSystem;
-use glutin::event::{Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use crate::framebuffer::{FrameCapture, FramebufferSystem, ScreenshotSource};
+use crate::shader;
+use crate::uniform_buffer::SharedUniforms;
+use glutin::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use glutin::{ContextError, CreationError, NotCurrent};
 #[cfg(feature = "imgui_inspect")]
 use imgui::Context;
 #[cfg(feature = "imgui_inspect")]
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
-use log::info;
 use log::LevelFilter;
+use log::{error, info, warn};
 use std::collections::VecDeque;
-use std::time::Instant;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct TimeStep {
@@ -75,9 +80,33 @@ impl TimeStep {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ScreenshotSettings {
+    /// key saving a screenshot, `None` disables the hotkey
+    pub key: Option<VirtualKeyCode>,
+    pub source: ScreenshotSource,
+    pub directory: PathBuf,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        Self {
+            key: Some(VirtualKeyCode::F12),
+            source: ScreenshotSource::Output,
+            directory: PathBuf::from("screenshots"),
+        }
+    }
+}
+
 pub struct Engine {
     title: String,
     size: (i32, i32),
+    screenshots: ScreenshotSettings,
+    /// recompile shaders created with `Shader::from_file` when their sources change
+    shader_hot_reload: bool,
+    asset_hot_reload: bool,
+    /// time per frame spent uploading assets loaded in the background
+    asset_upload_budget: Duration,
     #[cfg(feature = "imgui_inspect")]
     debug_layer: bool,
 }
@@ -87,13 +116,198 @@ impl Default for Engine {
         Self {
             title: String::from("Doppler demo"),
             size: (1280, 720),
+            screenshots: ScreenshotSettings::default(),
+            shader_hot_reload: cfg!(debug_assertions),
+            asset_hot_reload: cfg!(debug_assertions),
+            asset_upload_budget: Duration::from_millis(consts::ASSET_UPLOAD_BUDGET),
             #[cfg(feature = "imgui_inspect")]
             debug_layer: true,
         }
     }
 }
 
+/// Result of a headless run.
+#[derive(Debug, Clone)]
+pub struct HeadlessFrame {
+    /// scene color buffer, before postprocessing
+    pub scene: FrameCapture,
+    /// final image, after postprocessing
+    pub output: FrameCapture,
+}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    Creation(CreationError),
+    Context(ContextError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Creation(e) => write!(f, "cannot create headless context: {}", e),
+            HeadlessError::Context(e) => write!(f, "cannot make headless context current: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<CreationError> for HeadlessError {
+    fn from(e: CreationError) -> Self {
+        HeadlessError::Creation(e)
+    }
+}
+
+impl From<ContextError> for HeadlessError {
+    fn from(e: ContextError) -> Self {
+        HeadlessError::Context(e)
+    }
+}
+
+/// Event loop kept alive for as long as a headless context built on top of it.
+type HeadlessEventLoop = Option<glutin::event_loop::EventLoop<()>>;
+
+/// Creates an offscreen GL context without any window.
+///
+/// When a display server is reachable the platform headless context (EGL pbuffer
+/// or surfaceless) is used, otherwise we fall back to OSMesa so rendering also
+/// works on machines without any display, e.g. under Mesa's software rasterizer.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+fn build_headless_context(
+    size: glutin::dpi::PhysicalSize<u32>,
+) -> Result<(HeadlessEventLoop, glutin::Context<NotCurrent>), CreationError> {
+    use glutin::platform::unix::{EventLoopExtUnix, HeadlessContextExt};
+    let builder = glutin::ContextBuilder::new().with_gl_profile(glutin::GlProfile::Core);
+    let has_display =
+        std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+    if has_display {
+        let event_loop = glutin::event_loop::EventLoop::new_any_thread();
+        let context = builder.build_headless(&event_loop, size)?;
+        Ok((Some(event_loop), context))
+    } else {
+        info!("No display found, using OSMesa context");
+        Ok((None, builder.build_osmesa(size)?))
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+fn build_headless_context(
+    size: glutin::dpi::PhysicalSize<u32>,
+) -> Result<(HeadlessEventLoop, glutin::Context<NotCurrent>), CreationError> {
+    let event_loop = glutin::event_loop::EventLoop::new();
+    let context = glutin::ContextBuilder::new()
+        .with_gl_profile(glutin::GlProfile::Core)
+        .build_headless(&event_loop, size)?;
+    Ok((Some(event_loop), context))
+}
+
 impl Engine {
+    pub fn with_size(mut self, width: i32, height: i32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn with_screenshots(mut self, settings: ScreenshotSettings) -> Self {
+        self.screenshots = settings;
+        self
+    }
+
+    /// Enabled by default in debug builds.
+    pub fn with_shader_hot_reload(mut self, enabled: bool) -> Self {
+        self.shader_hot_reload = enabled;
+        self
+    }
+
+    /// Reloads models and textures when their files change, enabled by default in debug builds.
+    pub fn with_asset_hot_reload(mut self, enabled: bool) -> Self {
+        self.asset_hot_reload = enabled;
+        self
+    }
+
+    /// Time per frame spent uploading assets queued with `AssetsCache::load_model_async`.
+    pub fn with_asset_upload_budget(mut self, budget: Duration) -> Self {
+        self.asset_upload_budget = budget;
+        self
+    }
+
+    /// Runs the client without a window for a fixed number of frames and returns
+    /// the images rendered in the last one.
+    pub fn run_headless<T: Client + Default>(
+        self,
+        frames: u32,
+    ) -> Result<HeadlessFrame, HeadlessError> {
+        self.run_headless_with(frames, T::default)
+    }
+
+    /// Same as `run_headless`, but the client is created by `create_client` once
+    /// the GL context is current, so it can be configured by the caller.
+    pub fn run_headless_with<T: Client, F: FnOnce() -> T>(
+        self,
+        frames: u32,
+        create_client: F,
+    ) -> Result<HeadlessFrame, HeadlessError> {
+        let _ = simple_logging::log_to_file("log.log", LevelFilter::Info);
+        info!("Starting headless engine!");
+        let size = glutin::dpi::PhysicalSize::new(self.size.0 as u32, self.size.1 as u32);
+        let (_event_loop, context) = build_headless_context(size)?;
+        let context = unsafe { context.make_current().map_err(|(_, e)| e)? };
+
+        gl::load_with(|symbol| context.get_proc_address(symbol));
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Viewport(0, 0, self.size.0, self.size.1);
+        }
+        let mut framebuffer = unsafe { FramebufferSystem::generate(self.size.0, self.size.1) };
+        let mut uniforms = SharedUniforms::default();
+        let mut assets_cache = AssetsCache::default();
+        let mut client = create_client();
+        client.load_assets(&mut assets_cache);
+        // frames have to be reproducible, so background loading is finished up front
+        let progress = assets_cache.finish_loading();
+        if progress.total > 0 {
+            client.on_assets_progress(&progress, &mut assets_cache);
+        }
+        info!("Assets loaded, rendering {} frames", frames);
+
+        for _ in 0..frames {
+            client.update(consts::HEADLESS_FRAME_TIME);
+            unsafe {
+                framebuffer.clear();
+                client.update_uniforms(&mut uniforms);
+                client.draw();
+                framebuffer.draw();
+            }
+        }
+
+        let frame = unsafe {
+            gl::Finish();
+            HeadlessFrame {
+                scene: framebuffer.read_color_buffer(),
+                output: framebuffer.read_output(),
+            }
+        };
+        drop(client);
+        drop(assets_cache);
+        drop(uniforms);
+        drop(framebuffer);
+        crate::gpu::report_leaks();
+        info!("Headless run finished");
+
+        Ok(frame)
+    }
+
     pub fn run<T: Client + Default + 'static>(self) {
         use crate::utils;
         let _ = simple_logging::log_to_file("log.log", LevelFilter::Info);
@@ -114,17 +328,15 @@ impl Engine {
 
         let gl_window = unsafe { gl_window.make_current().unwrap() };
 
-        if utils::path_exists(std::path::Path::new("resources/icon.png")) {
-            use image2::image::Image;
-            use image2::{io, ImagePtr, Rgba};
-            let img: ImagePtr<u8, Rgba> =
-                io::read_u8(std::path::Path::new("resources/icon.png")).unwrap();
-            let img_data = img.data().to_vec();
-            let (x, y, _) = img.shape();
-            let icon = glutin::window::Icon::from_rgba(img_data, x as u32, y as u32);
-            if icon.is_ok() {
-                gl_window.window().set_window_icon(Some(icon.unwrap()));
+        match utils::decode_image("resources/icon.png", "png") {
+            Ok(img) => {
+                let icon =
+                    glutin::window::Icon::from_rgba(img.data, img.width as u32, img.height as u32);
+                if let Ok(icon) = icon {
+                    gl_window.window().set_window_icon(Some(icon));
+                }
             }
+            Err(e) => warn!("Window icon not set: {}", e),
         }
         info!(
             "Pixel format of the window's GL context: {:?}",
@@ -210,6 +422,7 @@ impl Engine {
             });
             (imgui, platform, renderer)
         };
+        let mut uniforms = SharedUniforms::default();
         info!("Creating AssetCache");
         let mut assets_cache = AssetsCache::default();
         info!("Creating client");
@@ -224,6 +437,11 @@ impl Engine {
         let _last_frame = std::time::Instant::now();
 
         let mut screensize = self.size;
+        let screenshots = self.screenshots.clone();
+        let mut take_screenshot = false;
+        let shader_hot_reload = self.shader_hot_reload;
+        let asset_hot_reload = self.asset_hot_reload;
+        let asset_upload_budget = self.asset_upload_budget;
         info!("Assets loaded");
 
         event_loop.run(move |event, _, control_flow| {
@@ -242,6 +460,16 @@ impl Engine {
                     }
                 }
                 Event::MainEventsCleared => {
+                    if shader_hot_reload {
+                        shader::poll_changes();
+                    }
+                    if asset_hot_reload {
+                        assets_cache.poll_changes();
+                    }
+                    if !assets_cache.load_progress().is_done() {
+                        let progress = assets_cache.process_uploads(asset_upload_budget);
+                        client.on_assets_progress(&progress, &mut assets_cache);
+                    }
                     timestep.update();
                     client.update(timestep.delta());
                     // other application-specific logic
@@ -289,6 +517,9 @@ impl Engine {
                         ..
                     } => match (virtual_code, state) {
                         (VirtualKeyCode::Escape, _) => *control_flow = ControlFlow::Exit,
+                        (code, ElementState::Pressed) if Some(code) == screenshots.key => {
+                            take_screenshot = true
+                        }
                         _ => client.on_keyboard(&virtual_code, &state),
                     },
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
@@ -312,9 +543,19 @@ impl Engine {
                 Event::RedrawRequested(_) => {
                     unsafe {
                         framebuffer.clear();
+                        client.update_uniforms(&mut uniforms);
                         client.draw();
                         framebuffer.draw();
                     }
+                    if take_screenshot {
+                        take_screenshot = false;
+                        match unsafe {
+                            framebuffer.screenshot(screenshots.source, &screenshots.directory)
+                        } {
+                            Ok(path) => info!("Screenshot saved to {}", path.display()),
+                            Err(e) => error!("Cannot save screenshot: {}", e),
+                        }
+                    }
 
                     #[cfg(feature = "imgui_inspect")]
                     if self.debug_layer {
@@ -378,6 +619,22 @@ impl Engine {
 
                                 ui.text(output);
                             });
+                        let shader_errors = crate::shader::recent_errors();
+                        if !shader_errors.is_empty() {
+                            Window::new(im_str!("Shader errors"))
+                                .size([670.0, 300.0], Condition::FirstUseEver)
+                                .position([offset, offset], Condition::FirstUseEver)
+                                .bg_alpha(0.8)
+                                .build(&ui, || {
+                                    if ui.button(im_str!("Clear"), [0.0, 0.0]) {
+                                        crate::shader::clear_recent_errors();
+                                    }
+                                    for e in shader_errors.iter().rev() {
+                                        ui.separator();
+                                        ui.text_colored([1.0, 0.4, 0.4, 1.0], e.to_string());
+                                    }
+                                });
+                        }
                         platform.prepare_render(&ui, gl_window.window());
                         renderer.render(ui);
                     }
@@ -0,0 +1,112 @@
+use crate::engine::HeadlessError;
+use crate::manifest::ManifestError;
+use crate::map::MapError;
+use crate::shader::ShaderError;
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, DopplerError>;
+
+#[derive(Debug)]
+pub enum DopplerError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Image {
+        path: String,
+        message: String,
+    },
+    Obj {
+        path: String,
+        source: tobj::LoadError,
+    },
+    Gltf {
+        path: String,
+        source: gltf::Error,
+    },
+    Shader(ShaderError),
+    /// malformed `#include` or other preprocessor problem, `line` is 1-based
+    Preprocess {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    Map(MapError),
+    Manifest(ManifestError),
+    Headless(HeadlessError),
+}
+
+impl DopplerError {
+    pub fn io(path: &str, source: io::Error) -> Self {
+        DopplerError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for DopplerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DopplerError::Io { path, source } => write!(f, "{}: {}", path, source),
+            DopplerError::Image { path, message } => {
+                write!(f, "cannot decode image {}: {}", path, message)
+            }
+            DopplerError::Obj { path, source } => {
+                write!(f, "cannot load model {}: {}", path, source)
+            }
+            DopplerError::Gltf { path, source } => {
+                write!(f, "cannot load model {}: {}", path, source)
+            }
+            DopplerError::Shader(e) => write!(f, "{}", e),
+            DopplerError::Preprocess {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            DopplerError::Map(e) => write!(f, "{}", e),
+            DopplerError::Manifest(e) => write!(f, "{}", e),
+            DopplerError::Headless(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DopplerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DopplerError::Io { source, .. } => Some(source),
+            DopplerError::Obj { source, .. } => Some(source),
+            DopplerError::Gltf { source, .. } => Some(source),
+            DopplerError::Shader(e) => Some(e),
+            DopplerError::Map(e) => Some(e),
+            DopplerError::Manifest(e) => Some(e),
+            DopplerError::Headless(e) => Some(e),
+            DopplerError::Image { .. } | DopplerError::Preprocess { .. } => None,
+        }
+    }
+}
+
+impl From<ShaderError> for DopplerError {
+    fn from(e: ShaderError) -> Self {
+        DopplerError::Shader(e)
+    }
+}
+
+impl From<MapError> for DopplerError {
+    fn from(e: MapError) -> Self {
+        DopplerError::Map(e)
+    }
+}
+
+impl From<ManifestError> for DopplerError {
+    fn from(e: ManifestError) -> Self {
+        DopplerError::Manifest(e)
+    }
+}
+
+impl From<HeadlessError> for DopplerError {
+    fn from(e: HeadlessError) -> Self {
+        DopplerError::Headless(e)
+    }
+}
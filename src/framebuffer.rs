@@ -1,33 +1,111 @@
+use crate::gpu::{GlKind, GlObject, MeshBuffers};
 use crate::shader::*;
 use gl::types::*;
 use log::{info, warn};
+use std::fs::File;
+use std::io::{self, BufWriter};
 use std::mem;
 use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
 use std::ptr;
 
-#[derive(Debug)]
-pub struct FramebufferSystem {
-    pub shader: Shader,
-    pub framebuffer: u32,
-    pub texture_color_buffer: u32,
-    vao: u32,
-    vbo: u32,
+/// RGBA8 pixels read back from the GPU, rows ordered from top to bottom.
+#[derive(Debug, Clone)]
+pub struct FrameCapture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
 }
 
-impl Drop for FramebufferSystem {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &self.vao);
-            gl::DeleteBuffers(1, &self.vbo);
-            gl::DeleteFramebuffers(1, &self.framebuffer);
+impl FrameCapture {
+    /// Builds a capture from pixels in OpenGL row order (bottom to top).
+    pub fn from_gl_rows(width: u32, height: u32, gl_pixels: &[u8]) -> FrameCapture {
+        let row = width as usize * 4;
+        let mut pixels = Vec::with_capacity(gl_pixels.len());
+        for line in gl_pixels.chunks_exact(row).rev() {
+            pixels.extend_from_slice(line);
         }
+        FrameCapture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        writer.write_image_data(&self.pixels).map_err(png_error)
     }
+
+    /// Loads 8-bit PNG file, converting it to RGBA if needed.
+    pub fn load_png<P: AsRef<Path>>(path: P) -> io::Result<FrameCapture> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(png_error)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(png_error)?;
+        buf.truncate(info.buffer_size());
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buf,
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+            png::ColorType::Indexed => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "indexed PNG was not expanded",
+                ))
+            }
+        };
+
+        Ok(FrameCapture {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+fn png_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// Which image should be captured by a screenshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenshotSource {
+    /// scene color buffer, before postprocessing
+    Scene,
+    /// final image shown on screen, after postprocessing
+    Output,
+}
+
+#[derive(Debug)]
+pub struct FramebufferSystem {
+    pub shader: Shader,
+    pub framebuffer: GlObject,
+    pub texture_color_buffer: GlObject,
+    /// depth and stencil attachment
+    pub renderbuffer: GlObject,
+    width: i32,
+    height: i32,
+    /// screen quad
+    quad: MeshBuffers,
 }
 
 impl FramebufferSystem {
     pub unsafe fn clear(&mut self) {
         // bind to framebuffer and draw scene as we normally would to color texture
-        gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer.id());
         gl::Enable(gl::DEPTH_TEST); // enable depth testing (is disabled for rendering screen-space quad)
 
         // make sure we clear the framebuffer's content
@@ -44,10 +122,64 @@ impl FramebufferSystem {
         gl::Clear(gl::COLOR_BUFFER_BIT);
 
         self.shader.use_program();
-        gl::BindVertexArray(self.vao);
-        gl::BindTexture(gl::TEXTURE_2D, self.texture_color_buffer); // use the color attachment texture as the texture of the quad plane
+        gl::BindVertexArray(self.quad.vao.id());
+        gl::BindTexture(gl::TEXTURE_2D, self.texture_color_buffer.id()); // use the color attachment texture as the texture of the quad plane
         gl::DrawArrays(gl::TRIANGLES, 0, 6);
     }
+
+    /// Reads the scene color attachment back into memory.
+    pub unsafe fn read_color_buffer(&self) -> FrameCapture {
+        let mut pixels = vec![0u8; self.width as usize * self.height as usize * 4];
+        gl::BindTexture(gl::TEXTURE_2D, self.texture_color_buffer.id());
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::GetTexImage(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        FrameCapture::from_gl_rows(self.width as u32, self.height as u32, &pixels)
+    }
+
+    /// Reads the post-processed image from the default framebuffer, must be called
+    /// after `draw` and before buffers are swapped.
+    pub unsafe fn read_output(&self) -> FrameCapture {
+        let mut pixels = vec![0u8; self.width as usize * self.height as usize * 4];
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            self.width,
+            self.height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+
+        FrameCapture::from_gl_rows(self.width as u32, self.height as u32, &pixels)
+    }
+
+    pub unsafe fn capture(&self, source: ScreenshotSource) -> FrameCapture {
+        match source {
+            ScreenshotSource::Scene => self.read_color_buffer(),
+            ScreenshotSource::Output => self.read_output(),
+        }
+    }
+
+    /// Saves a screenshot as timestamped PNG file in `dir` and returns its path.
+    /// `ScreenshotSource::Output` has to be captured after `draw`.
+    pub unsafe fn screenshot(&self, source: ScreenshotSource, dir: &Path) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("screenshot_{}.png", crate::utils::timestamp()));
+        self.capture(source).save_png(&path)?;
+
+        Ok(path)
+    }
+
     pub unsafe fn generate(scr_width: i32, scr_height: i32) -> Self {
         info!(
             "Generating new framebuffer with dimensions {}x{}",
@@ -66,11 +198,10 @@ impl FramebufferSystem {
         ];
 
         // screen quad VAO
-        let (mut quad_vao, mut quad_vbo) = (0, 0);
-        gl::GenVertexArrays(1, &mut quad_vao);
-        gl::GenBuffers(1, &mut quad_vbo);
-        gl::BindVertexArray(quad_vao);
-        gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo);
+        let quad_vao = GlObject::generate(GlKind::VertexArray);
+        let quad_vbo = GlObject::generate(GlKind::Buffer);
+        gl::BindVertexArray(quad_vao.id());
+        gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo.id());
         gl::BufferData(
             gl::ARRAY_BUFFER,
             (quad_vert.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
@@ -90,19 +221,17 @@ impl FramebufferSystem {
             (2 * mem::size_of::<GLfloat>()) as *const c_void,
         );
         shader.use_program();
-        shader.setInt(c_str!("screenTexture"), 0);
-        shader.setFloat(c_str!("screen_width"), scr_width as f32);
-        shader.setFloat(c_str!("screen_height"), scr_height as f32);
+        shader.set_uniform("screenTexture", 0);
+        shader.set_uniform("screen_width", scr_width as f32);
+        shader.set_uniform("screen_height", scr_height as f32);
 
         // framebuffer configuration
         // -------------------------
-        let mut framebuffer = 0;
-        gl::GenFramebuffers(1, &mut framebuffer);
-        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        let framebuffer = GlObject::generate(GlKind::Framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id());
         // create a color attachment texture
-        let mut texture_color_buffer = 0;
-        gl::GenTextures(1, &mut texture_color_buffer);
-        gl::BindTexture(gl::TEXTURE_2D, texture_color_buffer);
+        let texture_color_buffer = GlObject::generate(GlKind::Texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture_color_buffer.id());
         gl::TexImage2D(
             gl::TEXTURE_2D,
             0,
@@ -120,13 +249,12 @@ impl FramebufferSystem {
             gl::FRAMEBUFFER,
             gl::COLOR_ATTACHMENT0,
             gl::TEXTURE_2D,
-            texture_color_buffer,
+            texture_color_buffer.id(),
             0,
         );
         // create a renderbuffer object for depth and stencil attachment (we won't be sampling these)
-        let mut rbo = 0;
-        gl::GenRenderbuffers(1, &mut rbo);
-        gl::BindRenderbuffer(gl::RENDERBUFFER, rbo);
+        let rbo = GlObject::generate(GlKind::Renderbuffer);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, rbo.id());
         gl::RenderbufferStorage(
             gl::RENDERBUFFER,
             gl::DEPTH24_STENCIL8,
@@ -137,7 +265,7 @@ impl FramebufferSystem {
             gl::FRAMEBUFFER,
             gl::DEPTH_STENCIL_ATTACHMENT,
             gl::RENDERBUFFER,
-            rbo,
+            rbo.id(),
         ); // now actually attach it
            // now that we actually created the framebuffer and added all attachments we want to check if it is actually complete now
         if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
@@ -149,9 +277,15 @@ impl FramebufferSystem {
 
         FramebufferSystem {
             texture_color_buffer,
+            renderbuffer: rbo,
             shader,
-            vao: quad_vao,
-            vbo: quad_vbo,
+            width: scr_width,
+            height: scr_height,
+            quad: MeshBuffers {
+                vao: quad_vao,
+                vbo: quad_vbo,
+                ebo: GlObject::none(GlKind::Buffer),
+            },
             framebuffer,
         }
     }
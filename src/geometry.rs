@@ -0,0 +1,449 @@
+//! Normal and tangent space generation for imported meshes.
+//!
+//! Tangents are computed with the MikkTSpace algorithm (the one Blender, Substance and the
+//! glTF sample models bake normal maps with), so baked normal maps shade without seams.
+use std::collections::HashMap;
+
+use crate::mesh::Vertex;
+use cgmath::prelude::*;
+use cgmath::Vector3;
+
+/// Triangles as vertex indices, triangles pointing outside of `vertices` are skipped.
+fn triangles(indices: &[u32], vertex_count: usize) -> impl Iterator<Item = [usize; 3]> + '_ {
+    indices
+        .chunks_exact(3)
+        .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+        .filter(move |t| t.iter().all(|&i| i < vertex_count))
+}
+
+fn normalize_or(v: Vector3<f32>, fallback: Vector3<f32>) -> Vector3<f32> {
+    if v.magnitude2() > f32::EPSILON {
+        v.normalize()
+    } else {
+        fallback
+    }
+}
+
+/// Smooth normals for meshes that come without them, triangles are weighted by area.
+pub fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![Vector3::zero(); vertices.len()];
+    for [a, b, c] in triangles(indices, vertices.len()) {
+        let (pa, pb, pc) = (
+            vertices[a].position,
+            vertices[b].position,
+            vertices[c].position,
+        );
+        // length of the cross product is twice the triangle area
+        let normal = (pb - pa).cross(pc - pa);
+        for &i in &[a, b, c] {
+            normals[i] += normal;
+        }
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = normalize_or(normal, Vector3::unit_y());
+    }
+}
+
+/// MikkTSpace treats `0.0 == -0.0` when welding, adding 0.0 turns -0.0 into 0.0.
+fn weld_key(vertex: &Vertex) -> [u32; 8] {
+    let bits = |x: f32| (x + 0.0).to_bits();
+    let (p, n, t) = (vertex.position, vertex.normal, vertex.text_coords);
+    [
+        bits(p.x),
+        bits(p.y),
+        bits(p.z),
+        bits(n.x),
+        bits(n.y),
+        bits(n.z),
+        bits(t.x),
+        bits(t.y),
+    ]
+}
+
+fn not_zero(x: f32) -> bool {
+    x.abs() > f32::MIN_POSITIVE
+}
+
+fn normalize_not_zero(v: Vector3<f32>) -> Vector3<f32> {
+    if not_zero(v.x) || not_zero(v.y) || not_zero(v.z) {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+/// `v` projected on the plane perpendicular to `normal`, normalized.
+fn project(v: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+    normalize_not_zero(v - normal * normal.dot(v))
+}
+
+/// Triangle state of the MikkTSpace algorithm.
+struct Triangle {
+    /// welded vertex of each corner
+    vertices: [usize; 3],
+    /// triangle across the edge from corner `i` to corner `i + 1`
+    neighbors: [Option<usize>; 3],
+    /// group of each corner
+    groups: [Option<usize>; 3],
+    /// normalized derivatives of the position along U and V, negated when mirrored
+    os: Vector3<f32>,
+    ot: Vector3<f32>,
+    orient_preserving: bool,
+    /// texture coordinates don't define a frame, the triangle joins any group
+    group_with_any: bool,
+}
+
+impl Triangle {
+    fn new(vertices: [usize; 3], mesh: &[Vertex]) -> Triangle {
+        let p = |corner: usize| mesh[vertices[corner]].position;
+        let t = |corner: usize| mesh[vertices[corner]].text_coords;
+        let (t21, t31) = (t(1) - t(0), t(2) - t(0));
+        let (d1, d2) = (p(1) - p(0), p(2) - p(0));
+        let signed_area = t21.x * t31.y - t21.y * t31.x;
+        let os = d1 * t31.y - d2 * t21.y;
+        let ot = d1 * -t31.x + d2 * t21.x;
+
+        let mut triangle = Triangle {
+            vertices,
+            neighbors: [None; 3],
+            groups: [None; 3],
+            os: Vector3::zero(),
+            ot: Vector3::zero(),
+            orient_preserving: signed_area > 0.0,
+            group_with_any: true,
+        };
+        if not_zero(signed_area) {
+            let sign = if triangle.orient_preserving {
+                1.0
+            } else {
+                -1.0
+            };
+            let (length_s, length_t) = (os.magnitude(), ot.magnitude());
+            if not_zero(length_s) {
+                triangle.os = os * (sign / length_s);
+            }
+            if not_zero(length_t) {
+                triangle.ot = ot * (sign / length_t);
+            }
+            triangle.group_with_any =
+                !(not_zero(length_s / signed_area.abs()) && not_zero(length_t / signed_area.abs()));
+        }
+        triangle
+    }
+
+    fn corner(&self, vertex: usize) -> usize {
+        self.vertices
+            .iter()
+            .position(|&v| v == vertex)
+            .expect("vertex of the triangle")
+    }
+
+    /// Edge going through both vertices, named after its first corner.
+    fn edge(&self, a: usize, b: usize) -> usize {
+        let has = |corner: usize| self.vertices[corner] == a || self.vertices[corner] == b;
+        match (has(0), has(1)) {
+            (true, true) => 0,
+            (true, false) => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Triangles sharing a vertex, connected through edges and with the same UV orientation.
+struct Group {
+    vertex: usize,
+    orient_preserving: bool,
+    triangles: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct TangentSpace {
+    tangent: Vector3<f32>,
+    orient_preserving: bool,
+}
+
+impl Default for TangentSpace {
+    fn default() -> Self {
+        TangentSpace {
+            tangent: Vector3::unit_x(),
+            orient_preserving: false,
+        }
+    }
+}
+
+/// Pairs triangles sharing an edge with opposite winding.
+fn connect_neighbors(triangles: &mut [Triangle]) {
+    let mut edges: Vec<(usize, usize, usize)> = triangles
+        .iter()
+        .enumerate()
+        .flat_map(|(f, triangle)| {
+            (0..3).map(move |i| {
+                let (a, b) = (triangle.vertices[i], triangle.vertices[(i + 1) % 3]);
+                (a.min(b), a.max(b), f)
+            })
+        })
+        .collect();
+    edges.sort_unstable();
+
+    for (i, &(a, b, f)) in edges.iter().enumerate() {
+        let edge = triangles[f].edge(a, b);
+        if triangles[f].neighbors[edge].is_some() {
+            continue;
+        }
+        let (start, end) = (
+            triangles[f].vertices[edge],
+            triangles[f].vertices[(edge + 1) % 3],
+        );
+        let found = edges[i + 1..]
+            .iter()
+            .take_while(|other| other.0 == a && other.1 == b)
+            .find_map(|&(_, _, t)| {
+                let other = triangles[t].edge(a, b);
+                let reversed = triangles[t].vertices[other] == end
+                    && triangles[t].vertices[(other + 1) % 3] == start;
+                if reversed && triangles[t].neighbors[other].is_none() {
+                    Some((t, other))
+                } else {
+                    None
+                }
+            });
+        if let Some((t, other)) = found {
+            triangles[f].neighbors[edge] = Some(t);
+            triangles[t].neighbors[other] = Some(f);
+        }
+    }
+}
+
+/// Splits the triangles around each vertex into groups, see `Group`.
+fn build_groups(triangles: &mut [Triangle]) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    for f in 0..triangles.len() {
+        for i in 0..3 {
+            if triangles[f].group_with_any || triangles[f].groups[i].is_some() {
+                continue;
+            }
+            let g = groups.len();
+            groups.push(Group {
+                vertex: triangles[f].vertices[i],
+                orient_preserving: triangles[f].orient_preserving,
+                triangles: vec![f],
+            });
+            triangles[f].groups[i] = Some(g);
+
+            // depth first through the edges touching the vertex, the left side first
+            let mut pending = vec![
+                triangles[f].neighbors[(i + 2) % 3],
+                triangles[f].neighbors[i],
+            ];
+            while let Some(next) = pending.pop() {
+                let t = match next {
+                    Some(t) => t,
+                    None => continue,
+                };
+                let group = &mut groups[g];
+                let triangle = &mut triangles[t];
+                let corner = triangle.corner(group.vertex);
+                if triangle.groups[corner].is_some() {
+                    continue;
+                }
+                if triangle.group_with_any && triangle.groups.iter().all(Option::is_none) {
+                    triangle.orient_preserving = group.orient_preserving;
+                }
+                if triangle.orient_preserving != group.orient_preserving {
+                    continue;
+                }
+                group.triangles.push(t);
+                triangle.groups[corner] = Some(g);
+                pending.push(triangle.neighbors[(corner + 2) % 3]);
+                pending.push(triangle.neighbors[corner]);
+            }
+        }
+    }
+    groups
+}
+
+/// Tangent at `vertex` averaged over `members`, each weighted by its corner angle.
+fn eval_tangent(
+    vertices: &[Vertex],
+    triangles: &[Triangle],
+    members: &[usize],
+    vertex: usize,
+) -> Vector3<f32> {
+    let normal = vertices[vertex].normal;
+    let mut tangent = Vector3::zero();
+    for triangle in members.iter().map(|&f| &triangles[f]) {
+        if triangle.group_with_any {
+            continue;
+        }
+        let corner = triangle.corner(vertex);
+        let position = |corner: usize| vertices[triangle.vertices[corner % 3]].position;
+        let p1 = position(corner);
+        let v1 = project(position(corner + 2) - p1, normal);
+        let v2 = project(position(corner + 1) - p1, normal);
+        let angle = (v1.dot(v2).clamp(-1.0, 1.0) as f64).acos() as f32;
+        tangent += project(triangle.os, normal) * angle;
+    }
+    normalize_not_zero(tangent)
+}
+
+/// Tangent space of each triangle corner, triangles sharing a vertex and a group get the same.
+fn generate_tangent_spaces(
+    vertices: &[Vertex],
+    triangles: &[Triangle],
+    groups: &[Group],
+) -> Vec<Option<TangentSpace>> {
+    // cos of the angular threshold, 180 degrees like `genTangSpaceDefault`
+    let threshold = -1.0;
+    let mut spaces = vec![None; triangles.len() * 3];
+    for group in groups {
+        let normal = vertices[group.vertex].normal;
+        let mut subgroups: Vec<(Vec<usize>, Vector3<f32>)> = Vec::new();
+        for &f in &group.triangles {
+            let triangle = &triangles[f];
+            let (os, ot) = (project(triangle.os, normal), project(triangle.ot, normal));
+            let mut members: Vec<usize> = group
+                .triangles
+                .iter()
+                .copied()
+                .filter(|&t| {
+                    let other = &triangles[t];
+                    triangle.group_with_any
+                        || other.group_with_any
+                        || t == f
+                        || (os.dot(project(other.os, normal)) > threshold
+                            && ot.dot(project(other.ot, normal)) > threshold)
+                })
+                .collect();
+            members.sort_unstable();
+
+            let tangent = match subgroups.iter().find(|(other, _)| *other == members) {
+                Some((_, tangent)) => *tangent,
+                None => {
+                    let tangent = eval_tangent(vertices, triangles, &members, group.vertex);
+                    subgroups.push((members, tangent));
+                    tangent
+                }
+            };
+            spaces[f * 3 + triangle.corner(group.vertex)] = Some(TangentSpace {
+                tangent,
+                orient_preserving: group.orient_preserving,
+            });
+        }
+    }
+    spaces
+}
+
+/// Fills `tangent` and `bitangent` from normals and texture coordinates with MikkTSpace.
+///
+/// Vertices are welded by position, normal and texture coordinates first. A vertex whose
+/// triangles end up with different tangent spaces, e.g. on a mirrored UV seam, is split:
+/// copies are appended to `vertices` and `indices` are pointed at them. Vertices outside
+/// of any valid triangle keep their tangents.
+pub fn generate_tangents(vertices: &mut Vec<Vertex>, indices: &mut [u32]) {
+    let mut welded = HashMap::new();
+    let welded: Vec<usize> = vertices
+        .iter()
+        .enumerate()
+        .map(|(i, vertex)| *welded.entry(weld_key(vertex)).or_insert(i))
+        .collect();
+
+    // welded corners of triangle `t` of `indices`
+    let corners = |t: usize| -> [usize; 3] {
+        let welded_corner = |c: usize| welded[indices[t * 3 + c] as usize];
+        [welded_corner(0), welded_corner(1), welded_corner(2)]
+    };
+    let mut order: Vec<usize> = (0..indices.len() / 3)
+        .filter(|&t| {
+            indices[t * 3..t * 3 + 3]
+                .iter()
+                .all(|&i| (i as usize) < vertices.len())
+        })
+        .collect();
+
+    // degenerate triangles are moved to the end, like `DegenPrologue`
+    let mut degenerate: Vec<bool> = order
+        .iter()
+        .map(|&t| {
+            let [a, b, c] = corners(t);
+            let p = |v: usize| vertices[v].position;
+            p(a) == p(b) || p(a) == p(c) || p(b) == p(c)
+        })
+        .collect();
+    let good = degenerate.iter().filter(|&&d| !d).count();
+    let mut next_good = 1;
+    for t in 0..good {
+        if !degenerate[t] {
+            next_good = next_good.max(t + 2);
+            continue;
+        }
+        while next_good < order.len() && degenerate[next_good] {
+            next_good += 1;
+        }
+        if next_good >= order.len() {
+            break;
+        }
+        order.swap(t, next_good);
+        degenerate.swap(t, next_good);
+        next_good += 1;
+    }
+
+    let mut triangles: Vec<Triangle> = order[..good]
+        .iter()
+        .map(|&t| Triangle::new(corners(t), vertices))
+        .collect();
+    connect_neighbors(&mut triangles);
+    let groups = build_groups(&mut triangles);
+    let mut spaces = generate_tangent_spaces(vertices, &triangles, &groups);
+
+    // corners of degenerate triangles copy the first good corner on the same welded vertex
+    let mut first_corner = HashMap::new();
+    for (corner, vertex) in triangles
+        .iter()
+        .flat_map(|triangle| triangle.vertices.iter())
+        .enumerate()
+    {
+        first_corner.entry(*vertex).or_insert(corner);
+    }
+    spaces.resize(order.len() * 3, None);
+    for t in good..order.len() {
+        let triangle = corners(order[t]);
+        for (corner, vertex) in triangle.iter().enumerate() {
+            if let Some(&source) = first_corner.get(vertex) {
+                spaces[t * 3 + corner] = spaces[source];
+            }
+        }
+    }
+
+    // one tangent space per vertex, split vertices whose corners disagree
+    let mut assigned: Vec<Option<TangentSpace>> = vec![None; vertices.len()];
+    let mut copies: Vec<Vec<usize>> = vec![Vec::new(); vertices.len()];
+    for (k, &t) in order.iter().enumerate() {
+        for corner in 0..3 {
+            let space = spaces[k * 3 + corner].unwrap_or_default();
+            let slot = t * 3 + corner;
+            let original = indices[slot] as usize;
+            let existing = std::iter::once(original)
+                .chain(copies[original].iter().copied())
+                .find(|&v| assigned[v] == Some(space));
+            match existing {
+                Some(v) => indices[slot] = v as u32,
+                None if assigned[original].is_none() => assigned[original] = Some(space),
+                None => {
+                    let copy = vertices.len();
+                    vertices.push(vertices[original].clone());
+                    assigned.push(Some(space));
+                    copies[original].push(copy);
+                    indices[slot] = copy as u32;
+                }
+            }
+        }
+    }
+
+    for (vertex, space) in vertices.iter_mut().zip(assigned) {
+        if let Some(space) = space {
+            let sign = if space.orient_preserving { 1.0 } else { -1.0 };
+            vertex.tangent = space.tangent;
+            vertex.bitangent = vertex.normal.cross(space.tangent) * sign;
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! glTF 2.0 import (`.gltf` and `.glb`), used by `Model::parse`.
+//!
+//! Every primitive of every glTF mesh becomes one `MeshData` in mesh-local space, the
+//! node tree in `ModelData::nodes` places them and may reuse a mesh in several nodes.
+//! Textures referencing image files are loaded through the cache like OBJ textures,
+//! images stored in the file are decoded right away and cached under
+//! `<model path>#<image index>`.
+use crate::error::{DopplerError, Result};
+use crate::geometry;
+use crate::mesh::Vertex;
+use crate::model::{self, MeshData, ModelData, Node, TextureRef};
+use crate::utils::{self, ImageData};
+use cgmath::prelude::*;
+use cgmath::{vec2, vec3, vec4, Matrix4, Vector3};
+use gltf::buffer;
+use gltf::image::{self, Format};
+use gltf::mesh::Mode;
+use log::{error, info, warn};
+use std::path::Path;
+
+struct Importer<'a> {
+    path: &'a str,
+    base: &'a Path,
+    buffers: Vec<buffer::Data>,
+    diffuse_path: Option<&'a str>,
+    skip_textures: bool,
+    data: ModelData,
+}
+
+/// Reads the glTF file and the buffers and images it references, see `Model::parse`.
+pub fn parse(path: &str, diffuse_path: Option<&str>, skip_textures: bool) -> Result<ModelData> {
+    let error = |source| DopplerError::Gltf {
+        path: path.into(),
+        source,
+    };
+    info!("Loading glTF model: {}", path);
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path).map_err(error)?;
+    let base = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let buffers = gltf::import_buffers(&document, Some(base), blob).map_err(error)?;
+
+    let mut importer = Importer {
+        path,
+        base,
+        buffers,
+        diffuse_path,
+        skip_textures,
+        data: ModelData {
+            directory: base.to_str().unwrap_or_default().into(),
+            ..ModelData::default()
+        },
+    };
+    // indices into `ModelData::meshes` of the primitives of each glTF mesh
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if let Some(data) = importer.read_primitive(&primitive) {
+                primitives.push(importer.data.meshes.len());
+                importer.data.meshes.push(data);
+            }
+        }
+        meshes.push(primitives);
+    }
+    importer.data.nodes = document
+        .nodes()
+        .map(|node| Node {
+            name: node.name().unwrap_or_default().into(),
+            transform: Matrix4::from(node.transform().matrix()),
+            meshes: node
+                .mesh()
+                .map(|mesh| meshes[mesh.index()].clone())
+                .unwrap_or_default(),
+            children: node.children().map(|child| child.index()).collect(),
+        })
+        .collect();
+
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => importer.data.roots = scene.nodes().map(|node| node.index()).collect(),
+        None => warn!("There is no scene in: {}", path),
+    }
+
+    Ok(importer.data)
+}
+
+impl<'a> Importer<'a> {
+    fn read_primitive(&mut self, primitive: &gltf::Primitive) -> Option<MeshData> {
+        if primitive.mode() != Mode::Triangles {
+            warn!(
+                "Skip primitive drawn as {:?} in: {}",
+                primitive.mode(),
+                self.path
+            );
+            return None;
+        }
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+        let positions: Vec<[f32; 3]> = match reader.read_positions() {
+            Some(positions) => positions.collect(),
+            None => {
+                warn!("Skip primitive without positions in: {}", self.path);
+                return None;
+            }
+        };
+        let normals: Vec<[f32; 3]> = reader
+            .read_normals()
+            .map(|normals| normals.collect())
+            .unwrap_or_default();
+        let tangents: Vec<[f32; 4]> = reader
+            .read_tangents()
+            .map(|tangents| tangents.collect())
+            .unwrap_or_default();
+        let colors: Vec<[f32; 4]> = reader
+            .read_colors(0)
+            .map(|colors| colors.into_rgba_f32().collect())
+            .unwrap_or_default();
+        let tex_coords: Vec<[f32; 2]> = reader
+            .read_tex_coords(0)
+            .map(|coords| coords.into_f32().collect())
+            .unwrap_or_default();
+        let mut indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        let direction = |v: Vector3<f32>| {
+            if v.is_zero() {
+                v
+            } else {
+                v.normalize()
+            }
+        };
+        let mut vertices = positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let mut vertex = Vertex {
+                    position: vec3(p[0], p[1], p[2]),
+                    ..Vertex::default()
+                };
+                if let Some(n) = normals.get(i) {
+                    vertex.normal = direction(vec3(n[0], n[1], n[2]));
+                }
+                if let Some(t) = tex_coords.get(i) {
+                    vertex.text_coords = vec2(t[0], t[1]);
+                }
+                if let Some(c) = colors.get(i) {
+                    vertex.color = vec4(c[0], c[1], c[2], c[3]);
+                }
+                if let Some(t) = tangents.get(i) {
+                    vertex.tangent = direction(vec3(t[0], t[1], t[2]));
+                    // w stores the handedness of the tangent space
+                    vertex.bitangent = vertex.normal.cross(vertex.tangent) * t[3];
+                }
+                vertex
+            })
+            .collect::<Vec<_>>();
+        if normals.is_empty() {
+            geometry::generate_normals(&mut vertices, &indices);
+        }
+        if tangents.is_empty() && !tex_coords.is_empty() {
+            geometry::generate_tangents(&mut vertices, &mut indices);
+        }
+
+        let mut textures = Vec::new();
+        if !self.skip_textures {
+            let material = primitive.material();
+            match material.pbr_metallic_roughness().base_color_texture() {
+                Some(info) => textures.push(self.texture(&info.texture(), "texture_diffuse")),
+                None => {
+                    if let Some(path) = self.diffuse_path {
+                        textures.push(model::diffuse_override(path, &self.data.directory));
+                    }
+                }
+            }
+            if let Some(normal) = material.normal_texture() {
+                textures.push(self.texture(&normal.texture(), "texture_normal"));
+            }
+        }
+
+        Some(MeshData {
+            vertices,
+            indices,
+            textures,
+        })
+    }
+
+    fn texture(&mut self, texture: &gltf::Texture, type_: &str) -> TextureRef {
+        let image = texture.source();
+        let source = image.source();
+        if let image::Source::Uri { uri, .. } = source {
+            if !uri.starts_with("data:") {
+                return TextureRef::new(&self.data.directory, &percent_decode(uri), type_);
+            }
+        }
+
+        let key = format!("{}#{}", self.path, image.index());
+        let texture = TextureRef::new(&self.data.directory, &key, type_);
+        if !self.data.embedded.iter().any(|(t, _)| t.file == key) {
+            let decoded = image::Data::from_source(source, Some(self.base), &self.buffers)
+                .map_err(|source| DopplerError::Gltf {
+                    path: key.clone(),
+                    source,
+                })
+                .and_then(|data| image_data(data, &key));
+            let decoded = decoded.unwrap_or_else(|e| {
+                error!("{}, using placeholder texture", e);
+                utils::checkerboard(64, 8)
+            });
+            self.data.embedded.push((texture.clone(), decoded));
+        }
+
+        texture
+    }
+}
+
+/// Converts 8-bit images to the RGB or RGBA data uploaded by `utils::upload_texture`.
+/// Decodes `%XX` escapes, glTF URIs are RFC 3986 encoded (`my%20tex.png`).
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn image_data(image: image::Data, key: &str) -> Result<ImageData> {
+    let (format, data) = match image.format {
+        Format::R8G8B8 => (gl::RGB, image.pixels),
+        Format::R8G8B8A8 => (gl::RGBA, image.pixels),
+        Format::R8 => (
+            gl::RGB,
+            image.pixels.iter().flat_map(|&p| [p, p, p]).collect(),
+        ),
+        Format::R8G8 => (
+            gl::RGBA,
+            image
+                .pixels
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+        ),
+        other => {
+            return Err(DopplerError::Image {
+                path: key.into(),
+                message: format!("unsupported pixel format {:?}", other),
+            })
+        }
+    };
+
+    Ok(ImageData {
+        width: image.width as i32,
+        height: image.height as i32,
+        format,
+        data,
+    })
+}
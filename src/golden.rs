@@ -0,0 +1,308 @@
+//! Golden-image regression checks.
+//!
+//! A `GoldenTest` renders a map headlessly and compares both the scene color
+//! buffer and the post-processed output against reference PNGs. A missing
+//! reference is an error, set `DOPPLER_BLESS=1` to record new references or
+//! overwrite existing ones after an intended visual change.
+use crate::assets_cache::AssetsCache;
+use crate::camera::Camera;
+use crate::client::Client;
+use crate::engine::{Engine, HeadlessError};
+use crate::framebuffer::FrameCapture;
+use crate::map::{Map, MapSave};
+use crate::uniform_buffer::SharedUniforms;
+use glutin::event::{ElementState, VirtualKeyCode};
+use log::{info, warn};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const BLESS_ENV: &str = "DOPPLER_BLESS";
+
+/// Pixel by pixel comparison of two captures of the same size.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// number of pixels with at least one channel differing by more than the tolerance
+    pub mismatched: usize,
+    pub max_difference: u8,
+    /// mismatched pixels in red over a darkened copy of the expected image
+    pub diff: FrameCapture,
+}
+
+pub fn compare(expected: &FrameCapture, actual: &FrameCapture, tolerance: u8) -> Comparison {
+    let mut mismatched = 0;
+    let mut max_difference = 0;
+    let mut diff = Vec::with_capacity(expected.pixels.len());
+    for (e, a) in expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        let difference = e
+            .iter()
+            .zip(a.iter())
+            .map(|(x, y)| (*x as i16 - *y as i16).unsigned_abs() as u8)
+            .max()
+            .unwrap_or_default();
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 12) as u8;
+            diff.extend_from_slice(&[luma, luma, luma, 255]);
+        }
+    }
+
+    Comparison {
+        mismatched,
+        max_difference,
+        diff: FrameCapture {
+            width: expected.width,
+            height: expected.height,
+            pixels: diff,
+        },
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Headless(HeadlessError),
+    Io(PathBuf, io::Error),
+    /// the reference was never recorded, the rendered image is at `actual`
+    MissingReference {
+        reference: PathBuf,
+        actual: PathBuf,
+    },
+    SizeMismatch {
+        reference: PathBuf,
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    Mismatch {
+        reference: PathBuf,
+        mismatched: usize,
+        max_difference: u8,
+        diff: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Headless(e) => write!(f, "{}", e),
+            GoldenError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            GoldenError::MissingReference { reference, actual } => write!(
+                f,
+                "{}: no reference image, rendered {} (set {}=1 to record it)",
+                reference.display(),
+                actual.display(),
+                BLESS_ENV
+            ),
+            GoldenError::SizeMismatch {
+                reference,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: expected {}x{} image, rendered {}x{}",
+                reference.display(),
+                expected.0,
+                expected.1,
+                actual.0,
+                actual.1
+            ),
+            GoldenError::Mismatch {
+                reference,
+                mismatched,
+                max_difference,
+                diff,
+            } => write!(
+                f,
+                "{}: {} pixels differ (max difference {}), see {}",
+                reference.display(),
+                mismatched,
+                max_difference,
+                diff.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<HeadlessError> for GoldenError {
+    fn from(e: HeadlessError) -> Self {
+        GoldenError::Headless(e)
+    }
+}
+
+pub struct GoldenTest {
+    pub name: String,
+    pub map: String,
+    /// optional objects list preloaded with `AssetsCache::load_all_from_file`
+    pub assets: Option<String>,
+    /// overrides the camera stored in the map
+    pub camera: Option<Camera>,
+    pub frames: u32,
+    pub size: (i32, i32),
+    /// maximum allowed difference per color channel
+    pub tolerance: u8,
+    pub reference_dir: PathBuf,
+    /// where rendered images and diffs are written
+    pub output_dir: PathBuf,
+}
+
+impl GoldenTest {
+    pub fn new(name: &str, map: &str) -> Self {
+        GoldenTest {
+            name: name.into(),
+            map: map.into(),
+            assets: None,
+            camera: None,
+            frames: 3,
+            size: (320, 180),
+            tolerance: 2,
+            reference_dir: PathBuf::from("tests/golden"),
+            output_dir: PathBuf::from("target/golden"),
+        }
+    }
+
+    pub fn with_assets(mut self, path: &str) -> Self {
+        self.assets = Some(path.into());
+        self
+    }
+
+    pub fn with_camera(mut self, camera: Camera) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn with_frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    pub fn with_size(mut self, width: i32, height: i32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Renders the map and checks both the scene and final images against references.
+    pub fn run(&self) -> Result<(), GoldenError> {
+        let frame = Engine::default()
+            .with_size(self.size.0, self.size.1)
+            .run_headless_with(self.frames, || GoldenClient {
+                map_path: self.map.clone(),
+                assets: self.assets.clone(),
+                camera: self.camera,
+                map: None,
+            })?;
+
+        self.check("scene", &frame.scene)?;
+        self.check("final", &frame.output)
+    }
+
+    /// Compares `actual` with the `<name>_<suffix>.png` reference, or records it when
+    /// `DOPPLER_BLESS` is set.
+    pub fn check(&self, suffix: &str, actual: &FrameCapture) -> Result<(), GoldenError> {
+        let file_name = format!("{}_{}.png", self.name, suffix);
+        let reference = self.reference_dir.join(&file_name);
+        if std::env::var_os(BLESS_ENV).is_some() {
+            warn!("Recording golden image {}", reference.display());
+            return write_png(&reference, actual);
+        }
+
+        let output = self.output_dir.join(&file_name);
+        write_png(&output, actual)?;
+        if !reference.exists() {
+            return Err(GoldenError::MissingReference {
+                reference,
+                actual: output,
+            });
+        }
+        let expected = FrameCapture::load_png(&reference)
+            .map_err(|e| GoldenError::Io(reference.clone(), e))?;
+        if (expected.width, expected.height) != (actual.width, actual.height) {
+            return Err(GoldenError::SizeMismatch {
+                reference,
+                expected: (expected.width, expected.height),
+                actual: (actual.width, actual.height),
+            });
+        }
+
+        let comparison = compare(&expected, actual, self.tolerance);
+        if comparison.mismatched > 0 {
+            let diff = self
+                .output_dir
+                .join(format!("{}_{}.diff.png", self.name, suffix));
+            write_png(&diff, &comparison.diff)?;
+            return Err(GoldenError::Mismatch {
+                reference,
+                mismatched: comparison.mismatched,
+                max_difference: comparison.max_difference,
+                diff,
+            });
+        }
+        info!("{} matches reference", file_name);
+
+        Ok(())
+    }
+}
+
+fn write_png(path: &Path, capture: &FrameCapture) -> Result<(), GoldenError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| GoldenError::Io(dir.into(), e))?;
+    }
+    capture
+        .save_png(path)
+        .map_err(|e| GoldenError::Io(path.into(), e))
+}
+
+struct GoldenClient {
+    map_path: String,
+    assets: Option<String>,
+    camera: Option<Camera>,
+    map: Option<Map>,
+}
+
+impl Client for GoldenClient {
+    fn load_assets(&mut self, cache: &mut AssetsCache) {
+        if let Some(assets) = &self.assets {
+            cache.load_all_from_file(assets);
+        }
+        let mut map = MapSave::load(&self.map_path, cache);
+        if let Some(camera) = self.camera {
+            map.camera = camera;
+        }
+        self.map = Some(map);
+    }
+
+    fn update(&mut self, _delta: f32) {}
+
+    fn on_keyboard(&mut self, _code: &VirtualKeyCode, _state: &ElementState) {}
+
+    fn on_mouse_scroll(&mut self, _yoffset: f32) {}
+
+    fn on_mouse_move(&mut self, _x: f32, _y: f32) {}
+
+    unsafe fn update_uniforms(&mut self, uniforms: &mut SharedUniforms) {
+        if let Some(map) = &self.map {
+            map.update_uniforms(uniforms);
+        }
+    }
+
+    unsafe fn draw(&mut self) {
+        if let Some(map) = &mut self.map {
+            map.draw();
+        }
+    }
+
+    #[cfg(feature = "imgui_inspect")]
+    fn debug_draw(&mut self, _ui: &imgui::Ui) {}
+}
@@ -0,0 +1,227 @@
+//! Owned GL objects and reference counted handles to them.
+//!
+//! `GlObject` deletes its object when dropped. Textures and mesh buffers shared between
+//! models and the `AssetsCache` are held through `TextureHandle` and `MeshHandle`, the
+//! object is deleted when the last clone goes away. Debug builds count live objects per
+//! kind, see `live_objects` and `report_leaks`.
+use log::{info, warn};
+use std::rc::Rc;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlKind {
+    Texture,
+    Buffer,
+    VertexArray,
+    Framebuffer,
+    Renderbuffer,
+    Program,
+}
+
+impl GlKind {
+    pub const ALL: [GlKind; 6] = [
+        GlKind::Texture,
+        GlKind::Buffer,
+        GlKind::VertexArray,
+        GlKind::Framebuffer,
+        GlKind::Renderbuffer,
+        GlKind::Program,
+    ];
+
+    #[cfg(debug_assertions)]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[cfg(debug_assertions)]
+static LIVE: [AtomicUsize; 6] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+#[cfg(debug_assertions)]
+fn track(kind: GlKind, created: bool) {
+    let counter = &LIVE[kind.index()];
+    if created {
+        counter.fetch_add(1, Ordering::Relaxed);
+    } else {
+        counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(not(debug_assertions))]
+fn track(_: GlKind, _: bool) {}
+
+/// Number of live objects of the given kind, always 0 in release builds.
+#[cfg(debug_assertions)]
+pub fn live_objects(kind: GlKind) -> usize {
+    LIVE[kind.index()].load(Ordering::Relaxed)
+}
+
+#[cfg(not(debug_assertions))]
+pub fn live_objects(_: GlKind) -> usize {
+    0
+}
+
+/// Logs objects that are still alive, meant to be called after everything was dropped.
+pub fn report_leaks() {
+    let leaked: Vec<String> = GlKind::ALL
+        .iter()
+        .map(|kind| (kind, live_objects(*kind)))
+        .filter(|(_, count)| *count > 0)
+        .map(|(kind, count)| format!("{} {:?}", count, kind))
+        .collect();
+    if leaked.is_empty() {
+        info!("No GL objects leaked");
+    } else {
+        warn!("GL objects still alive: {}", leaked.join(", "));
+    }
+}
+
+/// GL object deleted on drop. Id 0 is accepted as "no object" and never deleted.
+#[derive(Debug)]
+pub struct GlObject {
+    kind: GlKind,
+    id: u32,
+}
+
+impl Drop for GlObject {
+    fn drop(&mut self) {
+        if self.id == 0 {
+            return;
+        }
+        unsafe {
+            match self.kind {
+                GlKind::Texture => gl::DeleteTextures(1, &self.id),
+                GlKind::Buffer => gl::DeleteBuffers(1, &self.id),
+                GlKind::VertexArray => gl::DeleteVertexArrays(1, &self.id),
+                GlKind::Framebuffer => gl::DeleteFramebuffers(1, &self.id),
+                GlKind::Renderbuffer => gl::DeleteRenderbuffers(1, &self.id),
+                GlKind::Program => gl::DeleteProgram(self.id),
+            }
+        }
+        track(self.kind, false);
+    }
+}
+
+impl GlObject {
+    /// Takes ownership of an existing object.
+    pub fn from_raw(kind: GlKind, id: u32) -> Self {
+        if id != 0 {
+            track(kind, true);
+        }
+        GlObject { kind, id }
+    }
+
+    /// Empty object of the given kind.
+    pub fn none(kind: GlKind) -> Self {
+        GlObject { kind, id: 0 }
+    }
+
+    pub unsafe fn generate(kind: GlKind) -> Self {
+        let mut id = 0;
+        match kind {
+            GlKind::Texture => gl::GenTextures(1, &mut id),
+            GlKind::Buffer => gl::GenBuffers(1, &mut id),
+            GlKind::VertexArray => gl::GenVertexArrays(1, &mut id),
+            GlKind::Framebuffer => gl::GenFramebuffers(1, &mut id),
+            GlKind::Renderbuffer => gl::GenRenderbuffers(1, &mut id),
+            GlKind::Program => id = gl::CreateProgram(),
+        }
+        Self::from_raw(kind, id)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn kind(&self) -> GlKind {
+        self.kind
+    }
+
+    /// Gives up ownership, the object has to be deleted by the caller.
+    pub fn into_raw(self) -> u32 {
+        let id = self.id;
+        if id != 0 {
+            track(self.kind, false);
+        }
+        std::mem::forget(self);
+        id
+    }
+}
+
+/// Shared 2D or cubemap texture, deleted when the last handle is dropped.
+#[derive(Debug, Clone)]
+pub struct TextureHandle(Rc<GlObject>);
+
+impl TextureHandle {
+    /// Takes ownership of a texture created with e.g. `utils::load_texture`.
+    pub fn from_raw(id: u32) -> Self {
+        TextureHandle(Rc::new(GlObject::from_raw(GlKind::Texture, id)))
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0.id()
+    }
+
+    /// Number of handles sharing the texture.
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+}
+
+/// Vertex array with its vertex and index buffers.
+#[derive(Debug)]
+pub struct MeshBuffers {
+    pub vao: GlObject,
+    pub vbo: GlObject,
+    pub ebo: GlObject,
+}
+
+impl Default for MeshBuffers {
+    fn default() -> Self {
+        MeshBuffers {
+            vao: GlObject::none(GlKind::VertexArray),
+            vbo: GlObject::none(GlKind::Buffer),
+            ebo: GlObject::none(GlKind::Buffer),
+        }
+    }
+}
+
+impl MeshBuffers {
+    pub unsafe fn generate() -> Self {
+        MeshBuffers {
+            vao: GlObject::generate(GlKind::VertexArray),
+            vbo: GlObject::generate(GlKind::Buffer),
+            ebo: GlObject::generate(GlKind::Buffer),
+        }
+    }
+}
+
+/// Mesh buffers shared by clones of a mesh, deleted when the last handle is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MeshHandle(Rc<MeshBuffers>);
+
+impl MeshHandle {
+    pub fn new(buffers: MeshBuffers) -> Self {
+        MeshHandle(Rc::new(buffers))
+    }
+
+    pub fn vao(&self) -> u32 {
+        self.0.vao.id()
+    }
+
+    pub fn buffers(&self) -> &MeshBuffers {
+        &self.0
+    }
+
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+}
@@ -13,18 +13,29 @@ pub use serde;
 pub mod macros;
 
 pub mod assets_cache;
+pub mod bounds;
 pub mod camera;
 pub mod client;
 pub mod components;
 pub mod consts;
 pub mod engine;
+pub mod error;
 pub mod framebuffer;
+pub mod geometry;
+pub mod gltf_loader;
+pub mod golden;
+pub mod gpu;
 #[cfg(feature = "imgui_inspect")]
 pub mod imgui_helper;
 pub mod light;
+pub mod loader;
+pub mod manifest;
 pub mod map;
 pub mod mesh;
 pub mod model;
+pub mod preprocessor;
 pub mod shader;
 pub mod sky;
+pub mod uniform;
+pub mod uniform_buffer;
 pub mod utils;
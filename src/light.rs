@@ -1,13 +1,15 @@
 #[cfg(feature = "imgui_inspect")]
 use crate::imgui_helper::*;
 use crate::shader::Shader;
-use cgmath::{vec3, Matrix4, Vector3};
+use crate::uniform_buffer::{SharedUniforms, Std140};
+use cgmath::{vec3, Vector3};
 #[cfg(feature = "imgui_inspect")]
 use imgui;
 #[cfg(feature = "imgui_inspect")]
 use imgui_inspect_derive::Inspect;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "imgui_inspect", derive(Inspect))]
 pub struct PointLight {
     #[cfg_attr(feature = "imgui_inspect", inspect(proxy_type = "CgmathVec3f32"))]
@@ -24,48 +26,17 @@ pub struct PointLight {
 }
 
 impl PointLight {
-    pub unsafe fn shader_update(&self, i: usize, shader: &Shader) {
-        let mut id = format!("pointLights[{}].position\0", i);
-        shader.set_vector3(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            &self.pos,
-        );
-        id = format!("pointLights[{}].ambient\0", i);
-        shader.set_vec3(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            self.ambient.x,
-            self.ambient.y,
-            self.ambient.z,
-        );
-        id = format!("pointLights[{}].diffuse\0", i);
-        shader.set_vec3(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            self.diffuse.x,
-            self.diffuse.y,
-            self.diffuse.z,
-        );
-        id = format!("pointLights[{}].specular\0", i);
-        shader.set_vec3(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            self.specular.x,
-            self.specular.y,
-            self.specular.z,
-        );
-        id = format!("pointLights[{}].constant\0", i);
-        shader.setFloat(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            self.constant,
-        );
-        id = format!("pointLights[{}].linear\0", i);
-        shader.setFloat(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            self.linear,
-        );
-        id = format!("pointLights[{}].quadratic\0", i);
-        shader.setFloat(
-            std::ffi::CStr::from_bytes_with_nul_unchecked(id.as_bytes()),
-            self.quadratic,
-        );
+    /// Writes the light as a `PointLight` struct of the `Lights` block.
+    pub fn write_std140(&self, out: &mut Std140) {
+        out.begin_struct()
+            .vec3(self.pos)
+            .float(self.constant)
+            .float(self.linear)
+            .float(self.quadratic)
+            .vec3(self.ambient)
+            .vec3(self.diffuse)
+            .vec3(self.specular)
+            .end_struct();
     }
 }
 
@@ -83,7 +54,7 @@ impl Default for PointLight {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "imgui_inspect", derive(Inspect))]
 pub struct DirectionalLight {
     #[cfg_attr(feature = "imgui_inspect", inspect(proxy_type = "CgmathVec3f32"))]
@@ -108,31 +79,14 @@ impl Default for DirectionalLight {
 }
 
 impl DirectionalLight {
-    pub unsafe fn shader_update(&self, shader: &Shader) {
-        shader.set_vec3(
-            c_str!("dirLight.direction"),
-            self.direction.x,
-            self.direction.y,
-            self.direction.z,
-        );
-        shader.set_vec3(
-            c_str!("dirLight.ambient"),
-            self.ambient.x,
-            self.ambient.y,
-            self.ambient.z,
-        );
-        shader.set_vec3(
-            c_str!("dirLight.diffuse"),
-            self.diffuse.x,
-            self.diffuse.y,
-            self.diffuse.z,
-        );
-        shader.set_vec3(
-            c_str!("dirLight.specular"),
-            self.specular.x,
-            self.specular.y,
-            self.specular.z,
-        );
+    /// Writes the light as the `DirLight` struct of the `Lights` block.
+    pub fn write_std140(&self, out: &mut Std140) {
+        out.begin_struct()
+            .vec3(self.direction)
+            .vec3(self.ambient)
+            .vec3(self.diffuse)
+            .vec3(self.specular)
+            .end_struct();
     }
 }
 
@@ -140,55 +94,60 @@ pub struct LightingSystem {
     pub shader: Shader,
     pub point_lights: [PointLight; 4],
     pub directional_light: DirectionalLight,
+    /// 0 uses dynamic lighting only, 1 replaces it with lighting baked into vertex colors
+    pub baked_lighting: f32,
 }
 
 impl LightingSystem {
-    pub unsafe fn prepare_for_draw(
-        &self,
-        projection: &Matrix4<f32>,
-        view: &Matrix4<f32>,
-        view_pos: &Vector3<f32>,
-    ) {
-        self.shader.use_program();
-
-        self.shader.set_mat4(c_str!("projection"), projection);
-        self.shader.set_mat4(c_str!("view"), view);
-        self.shader.set_vector3(c_str!("viewPos"), view_pos);
-        self.shader.setFloat(c_str!("material.shininess"), 32.0);
+    /// Uploads the lights to the `Lights` block shared with every program.
+    pub unsafe fn update_uniforms(&self, uniforms: &mut SharedUniforms) {
+        uniforms.update_lights(&self.directional_light, &self.point_lights);
+    }
 
-        self.directional_light.shader_update(&self.shader);
+    /// Binds the lighting shader, the camera and lights come from the shared blocks.
+    pub unsafe fn prepare_for_draw(&mut self) {
+        self.shader.use_program();
+        self.shader.set_uniform("material.shininess", 32.0);
+        self.shader
+            .set_uniform("bakedLighting", self.baked_lighting.clamp(0.0, 1.0));
+    }
+}
 
-        for (i, v) in self.point_lights.iter().enumerate() {
-            v.shader_update(i, &self.shader);
-        }
+impl LightingSystem {
+    pub fn default_point_lights() -> [PointLight; 4] {
+        [
+            PointLight {
+                pos: vec3(0.7, 5.0, 2.0),
+                ..PointLight::default()
+            },
+            PointLight {
+                pos: vec3(2.3, 3.3, -4.0),
+                ..PointLight::default()
+            },
+            PointLight {
+                pos: vec3(-4.0, 4.0, -12.0),
+                ..PointLight::default()
+            },
+            PointLight {
+                pos: vec3(0.0, 2.0, -3.0),
+                ..PointLight::default()
+            },
+        ]
     }
 }
 
 impl Default for LightingSystem {
     fn default() -> Self {
+        let point_lights = Self::default_point_lights();
+        let count = point_lights.len().to_string();
         LightingSystem {
-            point_lights: [
-                PointLight {
-                    pos: vec3(0.7, 5.0, 2.0),
-                    ..PointLight::default()
-                },
-                PointLight {
-                    pos: vec3(2.3, 3.3, -4.0),
-                    ..PointLight::default()
-                },
-                PointLight {
-                    pos: vec3(-4.0, 4.0, -12.0),
-                    ..PointLight::default()
-                },
-                PointLight {
-                    pos: vec3(0.0, 2.0, -3.0),
-                    ..PointLight::default()
-                },
-            ],
+            point_lights,
             directional_light: DirectionalLight::default(),
-            shader: Shader::from_file(
+            baked_lighting: 0.0,
+            shader: Shader::from_file_with_defines(
                 "resources/shaders/multiple_lights.vs",
                 "resources/shaders/multiple_lights.fs",
+                &[("NR_POINT_LIGHTS", &count)],
             ),
         }
     }
@@ -0,0 +1,324 @@
+//! Background asset loading.
+//!
+//! Model parsing and image decoding run on worker threads, only the GL upload happens on the
+//! render thread in `AssetsCache::process_uploads`, limited to a time budget per frame.
+//! A model is uploaded once all of its textures are in the cache, images embedded in glTF
+//! files are decoded together with the model.
+use crate::assets_cache::AssetsCache;
+use crate::consts;
+use crate::error::{DopplerError, Result};
+use crate::gpu::TextureHandle;
+use crate::mesh::Texture;
+use crate::model::{Model, ModelData, TextureRef};
+use crate::utils::{self, ImageData};
+use log::{error, info};
+use std::collections::{HashSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Assets queued for background loading and how many of them are ready to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadProgress {
+    pub total: usize,
+    pub done: usize,
+}
+
+impl LoadProgress {
+    /// Between 0 and 1, 1 when nothing is queued.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done >= self.total
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Job {
+    Model {
+        path: String,
+        texture: Option<String>,
+    },
+    Texture(TextureRef),
+}
+
+enum Loaded {
+    Model {
+        path: String,
+        texture: Option<String>,
+        result: Result<ModelData>,
+    },
+    Texture {
+        texture: TextureRef,
+        result: Result<ImageData>,
+    },
+}
+
+impl Job {
+    fn run(self) -> Loaded {
+        match self {
+            Job::Model { path, texture } => {
+                let result = Model::parse(&path, texture.as_deref(), false);
+                Loaded::Model {
+                    path,
+                    texture,
+                    result,
+                }
+            }
+            Job::Texture(texture) => {
+                let path = texture.full_path();
+                let result = utils::decode_image(&path, utils::image_format(&path));
+                Loaded::Texture { texture, result }
+            }
+        }
+    }
+
+    /// Result reported when a decoder panics, so the job still counts as finished.
+    fn panicked(self) -> Loaded {
+        match self {
+            Job::Model { path, texture } => Loaded::Model {
+                texture,
+                result: Err(DopplerError::io(
+                    &path,
+                    std::io::Error::new(std::io::ErrorKind::Other, "model parser panicked"),
+                )),
+                path,
+            },
+            Job::Texture(texture) => Loaded::Texture {
+                result: Err(DopplerError::Image {
+                    path: texture.full_path(),
+                    message: "image decoder panicked".into(),
+                }),
+                texture,
+            },
+        }
+    }
+}
+
+fn spawn_workers(count: usize) -> (Sender<Job>, Receiver<Loaded>) {
+    let (job_sender, jobs) = mpsc::channel::<Job>();
+    let (result_sender, results) = mpsc::channel();
+    let jobs = Arc::new(Mutex::new(jobs));
+    for i in 0..count {
+        let jobs = Arc::clone(&jobs);
+        let results = result_sender.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("asset-loader-{}", i))
+            .spawn(move || loop {
+                let job = match jobs.lock() {
+                    Ok(jobs) => jobs.recv(),
+                    Err(_) => return,
+                };
+                // the loader was dropped
+                let job = match job {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                let loaded = panic::catch_unwind(AssertUnwindSafe(|| job.clone().run()))
+                    .unwrap_or_else(|_| job.panicked());
+                if results.send(loaded).is_err() {
+                    return;
+                }
+            });
+        if let Err(e) = spawned {
+            error!("Cannot spawn asset loader thread: {}", e);
+        }
+    }
+
+    (job_sender, results)
+}
+
+/// Worker threads and the assets decoded by them, owned by the `AssetsCache`.
+#[derive(Default)]
+pub(crate) struct AsyncLoader {
+    workers: Option<(Sender<Job>, Receiver<Loaded>)>,
+    /// paths of queued models and file names of queued textures
+    pending_models: HashSet<(String, Option<String>)>,
+    pending_textures: HashSet<String>,
+    /// decoded, waiting for upload
+    /// model path, diffuse texture override and the parsed model
+    models: VecDeque<(String, Option<String>, Result<ModelData>)>,
+    textures: VecDeque<(TextureRef, Result<ImageData>)>,
+    progress: LoadProgress,
+}
+
+impl AsyncLoader {
+    pub fn progress(&self) -> LoadProgress {
+        self.progress
+    }
+
+    fn send(&mut self, job: Job) {
+        if self.progress.is_done() {
+            self.progress = LoadProgress::default();
+        }
+        self.progress.total += 1;
+        let (jobs, _) = self
+            .workers
+            .get_or_insert_with(|| spawn_workers(consts::ASSET_LOADER_THREADS));
+        if let Err(mpsc::SendError(job)) = jobs.send(job) {
+            // no worker could be spawned, decode on this thread instead
+            let loaded = job.run();
+            self.receive(loaded);
+        }
+    }
+
+    pub fn queue_model(&mut self, path: &str, texture: Option<&str>) {
+        if !self
+            .pending_models
+            .insert((path.into(), texture.map(String::from)))
+        {
+            return;
+        }
+        self.send(Job::Model {
+            path: path.into(),
+            texture: texture.map(String::from),
+        });
+    }
+
+    fn queue_texture(&mut self, texture: &TextureRef) {
+        if !self.pending_textures.insert(texture.file.clone()) {
+            return;
+        }
+        self.send(Job::Texture(texture.clone()));
+    }
+
+    fn receive(&mut self, loaded: Loaded) {
+        match loaded {
+            Loaded::Model {
+                path,
+                texture,
+                result,
+            } => self.models.push_back((path, texture, result)),
+            Loaded::Texture { texture, result } => self.textures.push_back((texture, result)),
+        }
+    }
+
+    fn receive_finished(&mut self, block: bool) {
+        let results = match &self.workers {
+            Some((_, results)) => results,
+            None => return,
+        };
+        let mut finished = Vec::new();
+        if block {
+            finished.extend(results.recv().ok());
+        }
+        finished.extend(results.try_iter());
+        for loaded in finished {
+            self.receive(loaded);
+        }
+    }
+
+    /// Uploads decoded assets until `budget` runs out, at least one asset per call.
+    pub fn upload(&mut self, cache: &mut AssetsCache, budget: Option<Duration>) -> LoadProgress {
+        self.receive_finished(false);
+        let start = Instant::now();
+        loop {
+            if !self.upload_next(cache) {
+                break;
+            }
+            if matches!(budget, Some(budget) if start.elapsed() >= budget) {
+                break;
+            }
+        }
+
+        self.progress
+    }
+
+    /// Blocks until every queued asset is uploaded.
+    pub fn finish(&mut self, cache: &mut AssetsCache) -> LoadProgress {
+        while !self.progress.is_done() {
+            self.upload(cache, None);
+            if !self.progress.is_done() {
+                self.receive_finished(true);
+            }
+        }
+
+        self.progress
+    }
+
+    fn upload_next(&mut self, cache: &mut AssetsCache) -> bool {
+        if let Some((texture, result)) = self.textures.pop_front() {
+            let id = match result {
+                Ok(image) => unsafe { utils::upload_texture(&image) },
+                Err(e) => {
+                    error!("{}, using placeholder texture", e);
+                    unsafe { utils::placeholder_texture() }
+                }
+            };
+            self.pending_textures.remove(&texture.file);
+            let fullpath = texture.full_path();
+            cache.insert_texture(
+                Texture {
+                    handle: TextureHandle::from_raw(id),
+                    type_: texture.type_,
+                    path: texture.file,
+                },
+                &fullpath,
+            );
+            self.progress.done += 1;
+            return true;
+        }
+
+        // decode the textures of parsed models, already queued ones are skipped
+        let mut missing = Vec::new();
+        for (path, diff_texture, result) in &self.models {
+            if cache.has_model_ext(path, diff_texture.as_deref()) {
+                continue;
+            }
+            if let Ok(data) = result {
+                for texture in data.external_textures() {
+                    if !cache.has_texture(&texture.file) {
+                        missing.push(texture.clone());
+                    }
+                }
+            }
+        }
+        for texture in &missing {
+            self.queue_texture(texture);
+        }
+
+        let ready = self
+            .models
+            .iter()
+            .position(|(path, diff_texture, result)| match result {
+                _ if cache.has_model_ext(path, diff_texture.as_deref()) => true,
+                Ok(data) => data
+                    .external_textures()
+                    .all(|texture| cache.has_texture(&texture.file)),
+                Err(_) => true,
+            });
+        let (path, diff_texture, result) = match ready.and_then(|i| self.models.remove(i)) {
+            Some(model) => model,
+            None => return false,
+        };
+        self.pending_models
+            .remove(&(path.clone(), diff_texture.clone()));
+        self.progress.done += 1;
+        if cache.has_model_ext(&path, diff_texture.as_deref()) {
+            // loaded synchronously in the meantime, components already hold that handle
+            info!("Skip model loaded before its upload: {}", path);
+            return true;
+        }
+        let model = match result {
+            Ok(data) => {
+                info!("Uploading model: {}", path);
+                Model::from_data(data, cache)
+            }
+            Err(e) => {
+                error!("{}, using placeholder model", e);
+                Model::placeholder()
+            }
+        };
+        cache.insert_model(&path, diff_texture.as_deref(), model);
+
+        true
+    }
+}
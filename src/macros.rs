@@ -2,6 +2,7 @@
 
 /// Macro to get c strings from literals without runtime overhead
 /// Literal must not contain any interior nul bytes!
+#[allow(unused_macros)]
 macro_rules! c_str {
     ($literal:expr) => {
         std::ffi::CStr::from_bytes_with_nul_unchecked(concat!($literal, "\0").as_bytes())
@@ -12,7 +13,10 @@ macro_rules! c_str {
 /// From https://stackoverflow.com/questions/40310483/how-to-get-pointer-offset-in-bytes/40310851#40310851
 #[allow(unused_macros)]
 macro_rules! offset_of {
-    ($ty:ty, $field:ident) => {
-        &(*(ptr::null() as *const $ty)).$field as *const _ as usize
-    };
+    ($ty:ty, $field:ident) => {{
+        // field address of an uninitialized value, a null pointer must not be dereferenced
+        let uninit = std::mem::MaybeUninit::<$ty>::uninit();
+        let base = uninit.as_ptr();
+        std::ptr::addr_of!((*base).$field) as usize - base as usize
+    }};
 }
@@ -0,0 +1,510 @@
+//! Asset manifest listing models, textures, cubemaps and shaders to preload.
+//!
+//! Manifests are YAML files, or JSON when the file has `.json` extension:
+//!
+//! ```yaml
+//! models:
+//!   - path: resources/objects/tree/tree_6_d.obj
+//!     texture: tree_e.png
+//!     tags: [foliage]
+//!     lods:
+//!       - path: resources/objects/tree/tree_6_a.obj
+//!         distance: 40.0
+//! textures:
+//!   - path: resources/objects/grass/foliage.png
+//! shaders:
+//!   - name: lamp
+//!     vertex: resources/shaders/lamp.vs
+//!     fragment: resources/shaders/lamp.fs
+//! ```
+use crate::utils::path_exists;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+pub const TEXTURE_TYPES: [&str; 4] = [
+    "texture_diffuse",
+    "texture_specular",
+    "texture_normal",
+    "texture_height",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AssetManifest {
+    #[serde(default)]
+    pub models: Vec<ModelEntry>,
+    #[serde(default)]
+    pub textures: Vec<TextureEntry>,
+    #[serde(default)]
+    pub cubemaps: Vec<CubemapEntry>,
+    #[serde(default)]
+    pub shaders: Vec<ShaderEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ModelEntry {
+    pub path: String,
+    /// diffuse texture used when the model has no material
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// textures replacing the ones from the model materials, keyed by texture type
+    #[serde(default)]
+    pub materials: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// lower detail variants, ordered by distance
+    #[serde(default)]
+    pub lods: Vec<LodEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LodEntry {
+    pub path: String,
+    /// camera distance from which this variant is used
+    pub distance: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TextureEntry {
+    pub path: String,
+    #[serde(default = "default_texture_type")]
+    pub kind: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_texture_type() -> String {
+    TEXTURE_TYPES[0].into()
+}
+
+/// Cubemap faces in order: right, left, top, bottom, back, front.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CubemapEntry {
+    pub name: String,
+    pub faces: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ShaderEntry {
+    pub name: String,
+    pub vertex: String,
+    pub fragment: String,
+    #[serde(default)]
+    pub geometry: Option<String>,
+}
+
+/// Problem found in a manifest, `line` is 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestIssue {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ManifestError {
+    pub path: String,
+    pub issues: Vec<ManifestIssue>,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid entries in {}", self.issues.len(), self.path)?;
+        for issue in &self.issues {
+            match issue.line {
+                Some(line) => write!(f, "\n  {}:{}: {}", self.path, line, issue.message)?,
+                None => write!(f, "\n  {}: {}", self.path, issue.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+const SECTIONS: [&str; 4] = ["models", "textures", "cubemaps", "shaders"];
+
+impl AssetManifest {
+    /// Reads and validates a manifest. Returns entries that passed validation
+    /// together with all problems found in the other ones.
+    pub fn load(path: &str) -> (AssetManifest, Vec<ManifestIssue>) {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                let issue = ManifestIssue {
+                    line: None,
+                    message: format!("cannot read file: {}", e),
+                };
+                return (AssetManifest::default(), vec![issue]);
+            }
+        };
+        let is_json = Path::new(path)
+            .extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("json"));
+
+        if is_json {
+            Self::parse_json(&text)
+        } else {
+            Self::parse_yaml(&text)
+        }
+    }
+
+    pub fn parse_yaml(text: &str) -> (AssetManifest, Vec<ManifestIssue>) {
+        let root: serde_yaml::Value = match serde_yaml::from_str(text) {
+            Ok(serde_yaml::Value::Null) => return (AssetManifest::default(), Vec::new()),
+            Ok(root) => root,
+            Err(e) => {
+                let issue = ManifestIssue {
+                    line: e.location().map(|l| l.line()),
+                    message: e.to_string(),
+                };
+                return (AssetManifest::default(), vec![issue]);
+            }
+        };
+        let mut sections = HashMap::new();
+        let mut issues = Vec::new();
+        match root {
+            serde_yaml::Value::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    let key = key.as_str().unwrap_or_default().to_string();
+                    match value {
+                        serde_yaml::Value::Sequence(entries) => {
+                            sections.insert(key, entries);
+                        }
+                        serde_yaml::Value::Null => {
+                            sections.insert(key, Vec::new());
+                        }
+                        _ => issues.push(ManifestIssue {
+                            line: None,
+                            message: format!("`{}` must be a list", key),
+                        }),
+                    }
+                }
+            }
+            _ => issues.push(ManifestIssue {
+                line: None,
+                message: "manifest must be a mapping of sections".into(),
+            }),
+        }
+
+        let lines = yaml_entry_lines(text);
+        let manifest = Self::from_sections(sections, &lines, &mut issues, |value| {
+            serde_yaml::from_value(value).map_err(|e| e.to_string())
+        });
+        (manifest, issues)
+    }
+
+    pub fn parse_json(text: &str) -> (AssetManifest, Vec<ManifestIssue>) {
+        let root: serde_json::Value = match serde_json::from_str(text) {
+            Ok(root) => root,
+            Err(e) => {
+                let issue = ManifestIssue {
+                    line: Some(e.line()),
+                    message: e.to_string(),
+                };
+                return (AssetManifest::default(), vec![issue]);
+            }
+        };
+        let mut sections = HashMap::new();
+        let mut issues = Vec::new();
+        match root {
+            serde_json::Value::Object(object) => {
+                for (key, value) in object {
+                    match value {
+                        serde_json::Value::Array(entries) => {
+                            sections.insert(key, entries);
+                        }
+                        serde_json::Value::Null => {
+                            sections.insert(key, Vec::new());
+                        }
+                        _ => issues.push(ManifestIssue {
+                            line: None,
+                            message: format!("`{}` must be a list", key),
+                        }),
+                    }
+                }
+            }
+            _ => issues.push(ManifestIssue {
+                line: None,
+                message: "manifest must be an object of sections".into(),
+            }),
+        }
+
+        let lines = json_entry_lines(text);
+        let manifest = Self::from_sections(sections, &lines, &mut issues, |value| {
+            serde_json::from_value(value).map_err(|e| e.to_string())
+        });
+        (manifest, issues)
+    }
+
+    fn from_sections<V, F>(
+        mut sections: HashMap<String, Vec<V>>,
+        lines: &HashMap<String, Vec<usize>>,
+        issues: &mut Vec<ManifestIssue>,
+        convert: F,
+    ) -> AssetManifest
+    where
+        F: Fn(V) -> Result<serde_json::Value, String>,
+    {
+        let mut unknown: Vec<&String> = sections
+            .keys()
+            .filter(|key| !SECTIONS.contains(&key.as_str()))
+            .collect();
+        unknown.sort();
+        for key in unknown {
+            issues.push(ManifestIssue {
+                line: None,
+                message: format!("unknown section `{}`", key),
+            });
+        }
+
+        let mut validator = Validator {
+            issues,
+            seen: HashSet::new(),
+        };
+        let mut manifest = AssetManifest::default();
+        let mut entries = |section: &str| -> Vec<(Option<usize>, serde_json::Value)> {
+            let values = sections.remove(section).unwrap_or_default();
+            values
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, value)| {
+                    let line = lines.get(section).and_then(|l| l.get(i)).copied();
+                    match convert(value) {
+                        Ok(value) => Some((line, value)),
+                        Err(message) => {
+                            validator.issues.push(ManifestIssue { line, message });
+                            None
+                        }
+                    }
+                })
+                .collect()
+        };
+        let models = entries("models");
+        let textures = entries("textures");
+        let cubemaps = entries("cubemaps");
+        let shaders = entries("shaders");
+
+        manifest.models = validator.entries(models, Validator::check_model);
+        manifest.textures = validator.entries(textures, Validator::check_texture);
+        manifest.cubemaps = validator.entries(cubemaps, Validator::check_cubemap);
+        manifest.shaders = validator.entries(shaders, Validator::check_shader);
+
+        manifest
+    }
+}
+
+struct Validator<'a> {
+    issues: &'a mut Vec<ManifestIssue>,
+    /// already used model/texture paths and cubemap/shader names
+    seen: HashSet<String>,
+}
+
+impl<'a> Validator<'a> {
+    fn entries<T: DeserializeOwned>(
+        &mut self,
+        values: Vec<(Option<usize>, serde_json::Value)>,
+        check: fn(&mut Self, &T) -> Vec<String>,
+    ) -> Vec<T> {
+        let mut valid = Vec::new();
+        for (line, value) in values {
+            let entry = match serde_json::from_value::<T>(value) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.issues.push(ManifestIssue {
+                        line,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let problems = check(self, &entry);
+            if problems.is_empty() {
+                valid.push(entry);
+            }
+            for message in problems {
+                self.issues.push(ManifestIssue { line, message });
+            }
+        }
+        valid
+    }
+
+    fn unique(&mut self, kind: &str, key: &str, problems: &mut Vec<String>) {
+        if !self.seen.insert(format!("{}:{}", kind, key)) {
+            problems.push(format!("duplicated {} `{}`", kind, key));
+        }
+    }
+
+    fn check_model(&mut self, entry: &ModelEntry) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.unique("model", &entry.path, &mut problems);
+        check_file(&entry.path, &mut problems);
+        let dir = parent_dir(&entry.path);
+        if let Some(texture) = &entry.texture {
+            check_file(&resolve_texture_path(&dir, texture), &mut problems);
+        }
+        for (kind, texture) in &entry.materials {
+            if !TEXTURE_TYPES.contains(&kind.as_str()) {
+                problems.push(format!("unknown texture type `{}`", kind));
+            }
+            check_file(&resolve_texture_path(&dir, texture), &mut problems);
+        }
+        let mut last_distance = 0.0;
+        for lod in &entry.lods {
+            check_file(&lod.path, &mut problems);
+            if lod.distance <= last_distance {
+                problems.push(format!(
+                    "lod `{}` distance must be greater than {}",
+                    lod.path, last_distance
+                ));
+            }
+            last_distance = lod.distance;
+        }
+        problems
+    }
+
+    fn check_texture(&mut self, entry: &TextureEntry) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.unique("texture", &entry.path, &mut problems);
+        check_file(&entry.path, &mut problems);
+        if !TEXTURE_TYPES.contains(&entry.kind.as_str()) {
+            problems.push(format!("unknown texture type `{}`", entry.kind));
+        }
+        problems
+    }
+
+    fn check_cubemap(&mut self, entry: &CubemapEntry) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.unique("cubemap", &entry.name, &mut problems);
+        if entry.faces.len() != 6 {
+            problems.push(format!(
+                "cubemap `{}` needs 6 faces, got {}",
+                entry.name,
+                entry.faces.len()
+            ));
+        }
+        for face in &entry.faces {
+            check_file(face, &mut problems);
+        }
+        problems
+    }
+
+    fn check_shader(&mut self, entry: &ShaderEntry) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.unique("shader", &entry.name, &mut problems);
+        check_file(&entry.vertex, &mut problems);
+        check_file(&entry.fragment, &mut problems);
+        if let Some(geometry) = &entry.geometry {
+            check_file(geometry, &mut problems);
+        }
+        problems
+    }
+}
+
+fn check_file(path: &str, problems: &mut Vec<String>) {
+    if !path_exists(Path::new(path)) {
+        problems.push(format!("file `{}` does not exist", path));
+    }
+}
+
+pub(crate) fn parent_dir(path: &str) -> String {
+    Path::new(path)
+        .parent()
+        .and_then(|p| p.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Texture paths without directory are relative to the model directory,
+/// same as textures referenced by model materials.
+pub(crate) fn resolve_texture_path(model_dir: &str, texture: &str) -> String {
+    if texture.contains('/') {
+        texture.to_string()
+    } else {
+        format!("{}/{}", model_dir, texture)
+    }
+}
+
+/// Finds 1-based lines where entries of each top level list start in block style YAML.
+fn yaml_entry_lines(text: &str) -> HashMap<String, Vec<usize>> {
+    let mut lines: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut section: Option<String> = None;
+    let mut entry_indent = None;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent == 0 && !trimmed.starts_with('-') {
+            section = trimmed.split(':').next().map(|key| key.trim().to_string());
+            entry_indent = None;
+            continue;
+        }
+        if let Some(section) = &section {
+            if trimmed.starts_with('-') && *entry_indent.get_or_insert(indent) == indent {
+                lines.entry(section.clone()).or_default().push(i + 1);
+            }
+        }
+    }
+    lines
+}
+
+/// Finds 1-based lines where entries of each top level array start in JSON.
+fn json_entry_lines(text: &str) -> HashMap<String, Vec<usize>> {
+    let mut lines: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut last_key = String::new();
+    let mut section: Option<String> = None;
+    let mut expect_entry = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if depth == 2 && expect_entry && !c.is_whitespace() && c != ',' && c != ']' {
+            if let Some(section) = &section {
+                lines.entry(section.clone()).or_default().push(line);
+            }
+            expect_entry = false;
+        }
+        match c {
+            '\n' => line += 1,
+            '"' => {
+                let mut string = String::new();
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        '\n' => line += 1,
+                        '\\' if !escaped => {
+                            escaped = true;
+                            continue;
+                        }
+                        '"' if !escaped => break,
+                        _ => (),
+                    }
+                    string.push(c);
+                    escaped = false;
+                }
+                if depth == 1 {
+                    last_key = string;
+                }
+            }
+            '{' | '[' => {
+                depth += 1;
+                if depth == 2 && c == '[' {
+                    section = Some(last_key.clone());
+                    expect_entry = true;
+                }
+            }
+            '}' | ']' => depth -= 1,
+            ',' if depth == 2 => expect_entry = true,
+            _ => (),
+        }
+    }
+    lines
+}
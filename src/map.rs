@@ -4,69 +4,229 @@ use crate::components::*;
 use crate::consts;
 use crate::light::*;
 use crate::math::{perspective, vec3, Deg, Matrix4, Point3};
-use crate::sky::Sky;
-use log::{error, info};
+use crate::sky::{Sky, SkyConfig};
+use crate::uniform_buffer::SharedUniforms;
+use crate::utils::path_exists;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MapObject {
-    pub model_hash: u64,
+    /// path of the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// legacy identifier, only read from maps saved before `model` was introduced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_hash: Option<u64>,
+    /// diffuse texture used when the model has no material
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<String>,
     pub transform: Transform,
 }
 
+impl MapObject {
+    fn model_name(&self) -> String {
+        match (&self.model, self.model_hash) {
+            (Some(path), _) => path.clone(),
+            (None, Some(hash)) => format!("legacy hash {}", hash),
+            (None, None) => "no model".into(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MapSave {
     pub objects: Vec<MapObject>,
     pub camera: Camera,
+    #[serde(default = "LightingSystem::default_point_lights")]
+    pub point_lights: [PointLight; 4],
+    #[serde(default)]
+    pub directional_light: DirectionalLight,
+    /// see `LightingSystem::baked_lighting`
+    #[serde(default)]
+    pub baked_lighting: f32,
+    #[serde(default)]
+    pub sky: SkyConfig,
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(e) => write!(f, "{}", e),
+            MapError::Yaml(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<io::Error> for MapError {
+    fn from(e: io::Error) -> Self {
+        MapError::Io(e)
+    }
+}
+
+impl From<serde_yaml::Error> for MapError {
+    fn from(e: serde_yaml::Error) -> Self {
+        MapError::Yaml(e)
+    }
 }
 
 impl MapSave {
-    pub fn save(map: &Map, _path: &str) {
+    pub fn from_map(map: &Map) -> MapSave {
         let mut objects = Vec::with_capacity(map.models.len());
         for m in &map.models {
             objects.push(MapObject {
-                model_hash: m.hash,
+                model: Some(m.path.clone()),
+                model_hash: None,
+                texture: m.texture.clone(),
                 transform: m.transform,
             });
         }
-        let ms = MapSave {
+        MapSave {
             camera: map.camera,
             objects,
-        };
-        match serde_yaml::to_string(&ms) {
-            Ok(result) => println!("{}", result),
-            Err(e) => println!("{:?}", e),
+            point_lights: map.lighting_system.point_lights,
+            directional_light: map.lighting_system.directional_light,
+            baked_lighting: map.lighting_system.baked_lighting,
+            sky: map.sky.config.clone(),
         }
     }
 
-    pub fn load(path: &str, cache: &mut AssetsCache) -> Map {
-        let mut map = Map::default();
-        info!("Loading map from file: {}", path);
+    pub fn read(path: &str) -> Result<MapSave, MapError> {
+        let savefile = fs::read_to_string(path)?;
 
-        use std::fs;
-        let savefile = fs::read_to_string(path);
-        if savefile.is_err() {
-            error!("Cannot read file {}: {:?}", path, savefile);
-            return map;
+        Ok(serde_yaml::from_str(&savefile)?)
+    }
+
+    /// Writes the save to a temporary file first and then renames it, so an
+    /// interrupted write never leaves a truncated map behind.
+    pub fn write(&self, path: &str) -> Result<(), MapError> {
+        let content = serde_yaml::to_string(self)?;
+        let tmp_path = format!("{}.tmp", path);
+        let result = fs::File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp_path, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
         }
 
-        let save: Result<MapSave, serde_yaml::Error> = serde_yaml::from_str(&savefile.unwrap());
+        Ok(result?)
+    }
 
-        if save.is_err() {
-            error!("Cannot parse file {}: {:?}", path, save);
-            return map;
-        }
+    pub fn save(map: &Map, path: &str) -> Result<(), MapError> {
+        Self::from_map(map).write(path)?;
+        info!("Map saved: {}", path);
 
-        for m in &save.unwrap().objects {
-            let model = cache.get_model_by_hash(&m.model_hash);
-            if model.is_none() {
-                continue;
-            }
-            map.models.push(ModelComponent {
-                transform: m.transform,
-                hash: m.model_hash,
-                model: model.unwrap(),
+        Ok(())
+    }
+
+    /// Model path and diffuse texture of every object, `None` for objects whose model
+    /// file is missing or whose legacy hash matches no known model. Nothing is loaded.
+    pub fn resolve_models(&self, cache: &AssetsCache) -> Vec<Option<(String, Option<String>)>> {
+        let mut legacy_models = None;
+        self.objects
+            .iter()
+            .map(|m| match (&m.model, m.model_hash) {
+                (Some(model_path), _) => {
+                    if cache.has_model(model_path) || path_exists(Path::new(model_path)) {
+                        Some((model_path.clone(), m.texture.clone()))
+                    } else {
+                        None
+                    }
+                }
+                (None, Some(hash)) => match legacy_models
+                    .get_or_insert_with(|| cache.legacy_models())
+                    .get(&hash)
+                {
+                    Some((model_path, texture)) => Some((
+                        model_path.clone(),
+                        m.texture.clone().or_else(|| texture.clone()),
+                    )),
+                    None => cache
+                        .legacy_model_file(hash)
+                        .map(|model_path| (model_path, m.texture.clone())),
+                },
+                (None, None) => None,
             })
+            .collect()
+    }
+
+    pub fn load(path: &str, cache: &mut AssetsCache) -> Map {
+        let mut map = Map::default();
+        info!("Loading map from file: {}", path);
+
+        let save = match Self::read(path) {
+            Ok(save) => save,
+            Err(e) => {
+                error!("Cannot load map {}: {}", path, e);
+                return map;
+            }
+        };
+
+        let mut unresolved = 0;
+        let legacy = save
+            .objects
+            .iter()
+            .filter(|m| m.model.is_none() && m.model_hash.is_some())
+            .count();
+        for (i, (m, resolved)) in save
+            .objects
+            .iter()
+            .zip(save.resolve_models(cache))
+            .enumerate()
+        {
+            match resolved {
+                Some((model_path, texture)) => map.models.push(ModelComponent {
+                    transform: m.transform,
+                    model: cache.get_model_ext(&model_path, texture.as_deref()),
+                    path: model_path,
+                    texture,
+                }),
+                None => {
+                    unresolved += 1;
+                    warn!(
+                        "Map {}: skipping object #{}, cannot resolve model ({})",
+                        path,
+                        i,
+                        m.model_name()
+                    );
+                }
+            }
+        }
+        if legacy > 0 {
+            warn!(
+                "Map {} references {} objects by legacy hash, save it again to store model paths",
+                path, legacy
+            );
+        }
+        if unresolved > 0 {
+            error!(
+                "Map {}: {} of {} objects could not be resolved",
+                path,
+                unresolved,
+                save.objects.len()
+            );
+        }
+        map.camera = save.camera;
+        map.lighting_system.point_lights = save.point_lights;
+        map.lighting_system.directional_light = save.directional_light;
+        map.lighting_system.baked_lighting = save.baked_lighting;
+        if save.sky != map.sky.config {
+            map.sky = unsafe { Sky::from_config(save.sky) };
         }
         info!("Map loaded: {}", path);
 
@@ -102,7 +262,8 @@ impl Default for Map {
 }
 
 impl Map {
-    pub unsafe fn draw(&mut self) {
+    /// Uploads the camera and lights, call it from `Client::update_uniforms`.
+    pub unsafe fn update_uniforms(&self, uniforms: &mut SharedUniforms) {
         use crate::math::prelude::*;
         // view/projection transformations
         let projection: Matrix4<f32> = perspective(
@@ -112,13 +273,16 @@ impl Map {
             1000.0,
         );
         let view = self.camera.get_view_matrix();
-        let view_pos = self.camera.position.to_vec();
-        self.lighting_system
-            .prepare_for_draw(&projection, &view, &view_pos);
+        uniforms.update_camera(&projection, &view, self.camera.position.to_vec());
+        self.lighting_system.update_uniforms(uniforms);
+    }
+
+    pub unsafe fn draw(&mut self) {
+        self.lighting_system.prepare_for_draw();
 
         for model in self.models.iter() {
             model.draw(&self.lighting_system.shader);
         }
-        self.sky.draw(view, projection);
+        self.sky.draw();
     }
 }
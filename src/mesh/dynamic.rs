@@ -0,0 +1,335 @@
+//! Meshes whose vertices change after creation: trails, debug geometry, deformable terrain.
+//!
+//! `DynamicMesh` keeps its data and updates parts of the GL buffers in place.
+//! `StreamingMesh` is rewritten every frame, it appends to a ring buffer and orphans the
+//! buffer when wrapping around, so new data never waits for draws still reading old data.
+//! Orphaning also drops every earlier range, so each push has to be drawn before the next.
+use std::marker::PhantomData;
+use std::mem::{size_of, size_of_val};
+use std::ops::Range;
+use std::os::raw::c_void;
+use std::ptr;
+
+use gl;
+use gl::types::*;
+
+use super::{apply_layout, bind_textures, Texture, Vertex, VertexFormat};
+use crate::gpu::{GlKind, GlObject, MeshBuffers};
+use crate::shader::Shader;
+
+/// Mesh uploaded with `gl::DYNAMIC_DRAW` that can be updated without rebuilding it.
+/// The buffers grow when `set_vertices` / `set_indices` need more room, they never shrink.
+#[derive(Debug)]
+pub struct DynamicMesh<V = Vertex> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u32>,
+    pub textures: Vec<Texture>,
+    /// primitive drawn, e.g. `gl::LINES` for debug geometry
+    pub mode: GLenum,
+
+    buffers: MeshBuffers,
+    /// allocated size of the buffers, in vertices and indices
+    vertex_capacity: usize,
+    index_capacity: usize,
+}
+
+impl<V: VertexFormat> DynamicMesh<V> {
+    pub fn new(vertices: Vec<V>, indices: Vec<u32>, textures: Vec<Texture>) -> DynamicMesh<V> {
+        let mut mesh = Self::with_capacity(0, 0);
+        mesh.textures = textures;
+        unsafe {
+            mesh.set_vertices(vertices);
+            mesh.set_indices(indices);
+        }
+        mesh
+    }
+
+    /// Empty mesh with room for `vertices` and `indices` before the buffers have to grow.
+    pub fn with_capacity(vertices: usize, indices: usize) -> DynamicMesh<V> {
+        let mut mesh = DynamicMesh {
+            vertices: Vec::with_capacity(vertices),
+            indices: Vec::with_capacity(indices),
+            textures: Vec::new(),
+            mode: gl::TRIANGLES,
+            buffers: MeshBuffers::default(),
+            vertex_capacity: 0,
+            index_capacity: 0,
+        };
+        unsafe { mesh.setupMesh(vertices, indices) }
+        mesh
+    }
+
+    pub fn vao(&self) -> u32 {
+        self.buffers.vao.id()
+    }
+
+    pub fn vertex_capacity(&self) -> usize {
+        self.vertex_capacity
+    }
+
+    pub fn index_capacity(&self) -> usize {
+        self.index_capacity
+    }
+
+    /// Overwrites `vertices[range]` with `data` and uploads only that part.
+    /// Panics when the range is out of bounds or its length differs from `data`, like
+    /// `copy_from_slice`.
+    pub unsafe fn update_vertices(&mut self, range: Range<usize>, data: &[V]) {
+        self.vertices[range.clone()].clone_from_slice(data);
+        upload_range(
+            gl::ARRAY_BUFFER,
+            self.buffers.vbo.id(),
+            &self.vertices[range.clone()],
+            range.start,
+        );
+    }
+
+    /// Overwrites `indices[range]` with `data` and uploads only that part.
+    pub unsafe fn update_indices(&mut self, range: Range<usize>, data: &[u32]) {
+        self.indices[range.clone()].copy_from_slice(data);
+        // the element buffer binding is part of the vertex array state
+        gl::BindVertexArray(self.vao());
+        upload_range(
+            gl::ELEMENT_ARRAY_BUFFER,
+            self.buffers.ebo.id(),
+            &self.indices[range.clone()],
+            range.start,
+        );
+        gl::BindVertexArray(0);
+    }
+
+    /// Replaces all vertices. The old storage is orphaned, so draws still using it
+    /// don't stall the upload.
+    pub unsafe fn set_vertices(&mut self, vertices: Vec<V>) {
+        self.vertex_capacity = self.vertex_capacity.max(vertices.len());
+        orphan(
+            gl::ARRAY_BUFFER,
+            self.buffers.vbo.id(),
+            self.vertex_capacity * size_of::<V>(),
+            gl::DYNAMIC_DRAW,
+        );
+        self.vertices = vertices;
+        upload_range(gl::ARRAY_BUFFER, self.buffers.vbo.id(), &self.vertices, 0);
+    }
+
+    /// Replaces all indices, see `set_vertices`.
+    pub unsafe fn set_indices(&mut self, indices: Vec<u32>) {
+        self.index_capacity = self.index_capacity.max(indices.len());
+        // the element buffer binding is part of the vertex array state
+        gl::BindVertexArray(self.vao());
+        orphan(
+            gl::ELEMENT_ARRAY_BUFFER,
+            self.buffers.ebo.id(),
+            self.index_capacity * size_of::<u32>(),
+            gl::DYNAMIC_DRAW,
+        );
+        self.indices = indices;
+        upload_range(
+            gl::ELEMENT_ARRAY_BUFFER,
+            self.buffers.ebo.id(),
+            &self.indices,
+            0,
+        );
+        gl::BindVertexArray(0);
+    }
+
+    /// render the whole mesh
+    pub unsafe fn Draw(&self, shader: &Shader) {
+        self.draw_range(shader, 0..self.indices.len());
+    }
+
+    /// Draws `indices[range]` only, the range is clamped to the indices.
+    pub unsafe fn draw_range(&self, shader: &Shader, range: Range<usize>) {
+        let end = range.end.min(self.indices.len());
+        if range.start >= end {
+            return;
+        }
+        bind_textures(&self.textures, shader);
+
+        gl::BindVertexArray(self.vao());
+        gl::DrawElements(
+            self.mode,
+            (end - range.start) as i32,
+            gl::UNSIGNED_INT,
+            (range.start * size_of::<u32>()) as *const c_void,
+        );
+        gl::BindVertexArray(0);
+
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+
+    unsafe fn setupMesh(&mut self, vertices: usize, indices: usize) {
+        let buffers = MeshBuffers::generate();
+        self.vertex_capacity = vertices;
+        self.index_capacity = indices;
+
+        gl::BindVertexArray(buffers.vao.id());
+        orphan(
+            gl::ARRAY_BUFFER,
+            buffers.vbo.id(),
+            vertices * size_of::<V>(),
+            gl::DYNAMIC_DRAW,
+        );
+        orphan(
+            gl::ELEMENT_ARRAY_BUFFER,
+            buffers.ebo.id(),
+            indices * size_of::<u32>(),
+            gl::DYNAMIC_DRAW,
+        );
+        apply_layout::<V>();
+        gl::BindVertexArray(0);
+
+        self.buffers = buffers;
+    }
+}
+
+/// Where the next write goes in a ring buffer of `capacity` elements.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RingCursor {
+    pub capacity: usize,
+    pub position: usize,
+}
+
+/// Space handed out by `RingCursor::reserve`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingWrite {
+    pub range: Range<usize>,
+    /// the cursor went back to the start (or the capacity grew): the buffer has to be
+    /// orphaned before writing. Draws queued before keep reading the old storage, earlier
+    /// ranges can't be drawn anymore.
+    pub orphan: bool,
+}
+
+impl RingCursor {
+    pub fn new(capacity: usize) -> Self {
+        RingCursor {
+            capacity,
+            position: 0,
+        }
+    }
+
+    /// Reserves `count` consecutive elements. Wraps around when they don't fit before the
+    /// end and doubles the capacity when they don't fit at all.
+    pub fn reserve(&mut self, count: usize) -> RingWrite {
+        let mut orphan = false;
+        if count > self.capacity {
+            self.capacity = count.max(self.capacity * 2);
+            self.position = 0;
+            orphan = true;
+        } else if self.position + count > self.capacity {
+            self.position = 0;
+            orphan = true;
+        }
+        let range = self.position..self.position + count;
+        self.position += count;
+
+        RingWrite { range, orphan }
+    }
+}
+
+/// Non-indexed vertices streamed every frame through a ring buffer.
+///
+/// A range is only valid until the next `push`, which may wrap around and orphan the
+/// buffer. Draw each range before pushing again:
+///
+/// ```ignore
+/// for trail in &trails {
+///     let range = mesh.push(&trail.points);
+///     mesh.draw(range);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StreamingMesh<V = Vertex> {
+    /// primitive drawn, e.g. `gl::LINE_STRIP` for trails
+    pub mode: GLenum,
+    buffers: MeshBuffers,
+    cursor: RingCursor,
+    vertex: PhantomData<V>,
+}
+
+impl<V: VertexFormat> StreamingMesh<V> {
+    /// Room for `capacity` vertices, a few frames worth avoids orphaning every frame.
+    pub fn new(capacity: usize, mode: GLenum) -> StreamingMesh<V> {
+        unsafe {
+            let buffers = MeshBuffers {
+                vao: GlObject::generate(GlKind::VertexArray),
+                vbo: GlObject::generate(GlKind::Buffer),
+                ebo: GlObject::none(GlKind::Buffer),
+            };
+            gl::BindVertexArray(buffers.vao.id());
+            orphan(
+                gl::ARRAY_BUFFER,
+                buffers.vbo.id(),
+                capacity * size_of::<V>(),
+                gl::STREAM_DRAW,
+            );
+            apply_layout::<V>();
+            gl::BindVertexArray(0);
+
+            StreamingMesh {
+                mode,
+                buffers,
+                cursor: RingCursor::new(capacity),
+                vertex: PhantomData,
+            }
+        }
+    }
+
+    pub fn vao(&self) -> u32 {
+        self.buffers.vao.id()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cursor.capacity
+    }
+
+    /// Uploads `vertices` after the previous ones and returns where they landed, for `draw`.
+    /// Ranges returned by earlier calls may point into orphaned storage afterwards, so
+    /// draw them first.
+    pub unsafe fn push(&mut self, vertices: &[V]) -> Range<usize> {
+        let write = self.cursor.reserve(vertices.len());
+        if write.orphan {
+            orphan(
+                gl::ARRAY_BUFFER,
+                self.buffers.vbo.id(),
+                self.cursor.capacity * size_of::<V>(),
+                gl::STREAM_DRAW,
+            );
+        }
+        upload_range(
+            gl::ARRAY_BUFFER,
+            self.buffers.vbo.id(),
+            vertices,
+            write.range.start,
+        );
+        write.range
+    }
+
+    /// Draws vertices returned by the last `push`, binding textures and uniforms is up to
+    /// the caller.
+    pub unsafe fn draw(&self, range: Range<usize>) {
+        if range.start >= range.end {
+            return;
+        }
+        gl::BindVertexArray(self.vao());
+        gl::DrawArrays(self.mode, range.start as i32, range.len() as i32);
+        gl::BindVertexArray(0);
+    }
+}
+
+/// (Re)allocates the storage of `buffer` without data, see "buffer orphaning".
+unsafe fn orphan(target: GLenum, buffer: u32, size: usize, usage: GLenum) {
+    gl::BindBuffer(target, buffer);
+    gl::BufferData(target, size as isize, ptr::null(), usage);
+}
+
+/// Writes `data` starting at element `first` of `buffer`.
+unsafe fn upload_range<V>(target: GLenum, buffer: u32, data: &[V], first: usize) {
+    gl::BindBuffer(target, buffer);
+    gl::BufferSubData(
+        target,
+        (first * size_of::<V>()) as isize,
+        size_of_val(data) as isize,
+        data.as_ptr() as *const c_void,
+    );
+}
@@ -0,0 +1,189 @@
+//! Description of the vertex attributes uploaded by `Mesh`.
+use cgmath::Vector3;
+use gl::types::*;
+use std::os::raw::c_void;
+
+/// Type of a single attribute component as stored in the vertex buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentType {
+    F32,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+}
+
+impl ComponentType {
+    pub fn gl_type(self) -> GLenum {
+        match self {
+            ComponentType::F32 => gl::FLOAT,
+            ComponentType::I8 => gl::BYTE,
+            ComponentType::U8 => gl::UNSIGNED_BYTE,
+            ComponentType::I16 => gl::SHORT,
+            ComponentType::U16 => gl::UNSIGNED_SHORT,
+            ComponentType::I32 => gl::INT,
+            ComponentType::U32 => gl::UNSIGNED_INT,
+        }
+    }
+
+    /// Size in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            ComponentType::I8 | ComponentType::U8 => 1,
+            ComponentType::I16 | ComponentType::U16 => 2,
+            ComponentType::F32 | ComponentType::I32 | ComponentType::U32 => 4,
+        }
+    }
+
+    pub fn is_integer(self) -> bool {
+        self != ComponentType::F32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexAttribute {
+    /// for debugging, the shader input is matched by `location`
+    pub name: &'static str,
+    pub location: u32,
+    pub component: ComponentType,
+    /// number of components, 1 to 4
+    pub count: i32,
+    /// integer components are read as floats in 0..1 (or -1..1) by the shader,
+    /// not normalized ones are read as integers (e.g. joint indices)
+    pub normalized: bool,
+    /// byte offset within the vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    pub fn new(
+        name: &'static str,
+        location: u32,
+        component: ComponentType,
+        count: i32,
+        offset: usize,
+    ) -> Self {
+        VertexAttribute {
+            name,
+            location,
+            component,
+            count,
+            normalized: false,
+            offset,
+        }
+    }
+
+    pub fn normalized(mut self) -> Self {
+        self.normalized = true;
+        self
+    }
+
+    /// Size in bytes.
+    pub fn size(&self) -> usize {
+        self.component.size() * self.count as usize
+    }
+}
+
+/// Attributes of one vertex and the distance between vertices in the buffer.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VertexLayout {
+    pub stride: usize,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(stride: usize) -> Self {
+        VertexLayout {
+            stride,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, attribute: VertexAttribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&VertexAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    /// Describes the first problem found: attributes sharing a location, not fitting in
+    /// the stride or with a wrong component count.
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            if !(1..=4).contains(&attribute.count) {
+                return Err(format!(
+                    "attribute {} has {} components, expected 1 to 4",
+                    attribute.name, attribute.count
+                ));
+            }
+            if attribute.offset + attribute.size() > self.stride {
+                return Err(format!(
+                    "attribute {} ends at byte {}, after the vertex stride {}",
+                    attribute.name,
+                    attribute.offset + attribute.size(),
+                    self.stride
+                ));
+            }
+            if let Some(other) = self.attributes[..i]
+                .iter()
+                .find(|other| other.location == attribute.location)
+            {
+                return Err(format!(
+                    "attributes {} and {} share location {}",
+                    other.name, attribute.name, attribute.location
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the attribute pointers of the bound vertex array for the bound vertex buffer.
+    pub unsafe fn apply(&self) {
+        let stride = self.stride as GLsizei;
+        for attribute in &self.attributes {
+            let offset = attribute.offset as *const c_void;
+            gl::EnableVertexAttribArray(attribute.location);
+            if attribute.component.is_integer() && !attribute.normalized {
+                gl::VertexAttribIPointer(
+                    attribute.location,
+                    attribute.count,
+                    attribute.component.gl_type(),
+                    stride,
+                    offset,
+                );
+            } else {
+                let normalized = if attribute.normalized {
+                    gl::TRUE
+                } else {
+                    gl::FALSE
+                };
+                gl::VertexAttribPointer(
+                    attribute.location,
+                    attribute.count,
+                    attribute.component.gl_type(),
+                    normalized,
+                    stride,
+                    offset,
+                );
+            }
+        }
+    }
+}
+
+/// Vertex types that can be stored in a `Mesh`, the layout has to match the
+/// `repr(C)` memory layout of the type.
+pub trait VertexFormat: Clone {
+    fn layout() -> VertexLayout;
+
+    /// Position used for the mesh bounds, formats without one get empty bounds.
+    fn position(&self) -> Option<Vector3<f32>> {
+        None
+    }
+}
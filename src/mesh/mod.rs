@@ -0,0 +1,302 @@
+#![allow(dead_code)]
+#![allow(non_snake_case)]
+
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::ptr;
+
+use cgmath::prelude::*;
+use cgmath::{vec4, Vector2, Vector3, Vector4};
+use gl;
+use log::error;
+
+use crate::bounds::Bounds;
+use crate::gpu::{MeshBuffers, MeshHandle, TextureHandle};
+use crate::shader::Shader;
+
+pub mod dynamic;
+pub mod layout;
+pub mod primitives;
+
+pub use self::dynamic::{DynamicMesh, RingCursor, RingWrite, StreamingMesh};
+pub use self::layout::{ComponentType, VertexAttribute, VertexFormat, VertexLayout};
+
+// NOTE: without repr(C) the compiler may reorder the fields or use different padding/alignment than C.
+// Depending on how you pass the data to OpenGL, this may be bad. In this case it's not strictly
+// necessary though because of the `offset!` macro used below in layout()
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct Vertex {
+    // position
+    pub position: Vector3<f32>,
+    // normal
+    pub normal: Vector3<f32>,
+    // texCoords
+    pub text_coords: Vector2<f32>,
+    // tangent
+    pub tangent: Vector3<f32>,
+    // bitangent
+    pub bitangent: Vector3<f32>,
+    // RGBA color, white when the model has no vertex colors
+    pub color: Vector4<f32>,
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Vertex {
+            position: Vector3::zero(),
+            normal: Vector3::zero(),
+            text_coords: Vector2::zero(),
+            tangent: Vector3::zero(),
+            bitangent: Vector3::zero(),
+            color: vec4(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl VertexFormat for Vertex {
+    fn layout() -> VertexLayout {
+        let (position, normal, text_coords, tangent, bitangent, color) = unsafe {
+            (
+                offset_of!(Vertex, position),
+                offset_of!(Vertex, normal),
+                offset_of!(Vertex, text_coords),
+                offset_of!(Vertex, tangent),
+                offset_of!(Vertex, bitangent),
+                offset_of!(Vertex, color),
+            )
+        };
+
+        VertexLayout::new(size_of::<Vertex>())
+            .with(VertexAttribute::new(
+                "position",
+                0,
+                ComponentType::F32,
+                3,
+                position,
+            ))
+            .with(VertexAttribute::new(
+                "normal",
+                1,
+                ComponentType::F32,
+                3,
+                normal,
+            ))
+            .with(VertexAttribute::new(
+                "text_coords",
+                2,
+                ComponentType::F32,
+                2,
+                text_coords,
+            ))
+            .with(VertexAttribute::new(
+                "tangent",
+                3,
+                ComponentType::F32,
+                3,
+                tangent,
+            ))
+            .with(VertexAttribute::new(
+                "bitangent",
+                4,
+                ComponentType::F32,
+                3,
+                bitangent,
+            ))
+            .with(VertexAttribute::new(
+                "color",
+                5,
+                ComponentType::F32,
+                4,
+                color,
+            ))
+    }
+
+    fn position(&self) -> Option<Vector3<f32>> {
+        Some(self.position)
+    }
+}
+
+/// Clones share the GL texture, it is deleted together with the last clone.
+#[derive(Clone, Debug)]
+pub struct Texture {
+    pub handle: TextureHandle,
+    pub type_: String,
+    pub path: String,
+}
+
+impl Texture {
+    pub fn id(&self) -> u32 {
+        self.handle.id()
+    }
+}
+
+/// Clones share the GL buffers, they are deleted together with the last clone.
+/// Vertices are uploaded as described by `V::layout()`.
+#[derive(Clone, Debug)]
+pub struct Mesh<V = Vertex> {
+    /*  Mesh Data  */
+    pub vertices: Vec<V>,
+    pub indices: Vec<u32>,
+    pub textures: Vec<Texture>,
+    /// model space bounds of `vertices`, see `update_bounds`
+    pub bounds: Bounds,
+
+    /*  Render data  */
+    buffers: MeshHandle,
+}
+
+impl<V: VertexFormat> Mesh<V> {
+    pub fn new(vertices: Vec<V>, indices: Vec<u32>, textures: Vec<Texture>) -> Mesh<V> {
+        let mut mesh = Mesh {
+            bounds: vertex_bounds(&vertices),
+            vertices,
+            indices,
+            textures,
+            buffers: MeshHandle::default(),
+        };
+        // now that we have all the required data, set the vertex buffers and its attribute pointers.
+        unsafe { mesh.setupMesh() }
+        mesh
+    }
+
+    pub fn vao(&self) -> u32 {
+        self.buffers.vao()
+    }
+
+    /// Has to be called after moving `vertices`, the GL buffers are not updated.
+    pub fn update_bounds(&mut self) {
+        self.bounds = vertex_bounds(&self.vertices);
+    }
+
+    /// render the mesh
+    pub unsafe fn Draw(&self, shader: &Shader) {
+        bind_textures(&self.textures, shader);
+
+        // draw mesh
+        gl::BindVertexArray(self.vao());
+        gl::DrawElements(
+            gl::TRIANGLES,
+            self.indices.len() as i32,
+            gl::UNSIGNED_INT,
+            ptr::null(),
+        );
+        gl::BindVertexArray(0);
+
+        // always good practice to set everything back to defaults once configured.
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+
+    unsafe fn setupMesh(&mut self) {
+        // create buffers/arrays
+        let buffers = MeshBuffers::generate();
+
+        gl::BindVertexArray(buffers.vao.id());
+        // load data into vertex buffers
+        gl::BindBuffer(gl::ARRAY_BUFFER, buffers.vbo.id());
+        // A great thing about structs with repr(C) is that their memory layout is sequential for all its items.
+        // The effect is that we can simply pass a pointer to the struct and it translates perfectly to a glm::vec3/2 array which
+        // again translates to 3/2 floats which translates to a byte array.
+        let size = (self.vertices.len() * size_of::<V>()) as isize;
+        let data = self.vertices.as_ptr() as *const c_void;
+        gl::BufferData(gl::ARRAY_BUFFER, size, data, gl::STATIC_DRAW);
+
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffers.ebo.id());
+        let size = (self.indices.len() * size_of::<u32>()) as isize;
+        let data = self.indices.as_ptr() as *const c_void;
+        gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, size, data, gl::STATIC_DRAW);
+
+        // set the vertex attribute pointers
+        apply_layout::<V>();
+
+        gl::BindVertexArray(0);
+        self.buffers = MeshHandle::new(buffers);
+    }
+}
+
+/// Sampler names for each texture type, indexed by the number of textures of that type
+/// bound before, so drawing doesn't format them every time.
+const SAMPLER_NAMES: [(&str, [&str; 4]); 4] = [
+    (
+        "texture_diffuse",
+        [
+            "texture_diffuse1",
+            "texture_diffuse2",
+            "texture_diffuse3",
+            "texture_diffuse4",
+        ],
+    ),
+    (
+        "texture_specular",
+        [
+            "texture_specular1",
+            "texture_specular2",
+            "texture_specular3",
+            "texture_specular4",
+        ],
+    ),
+    (
+        "texture_normal",
+        [
+            "texture_normal1",
+            "texture_normal2",
+            "texture_normal3",
+            "texture_normal4",
+        ],
+    ),
+    (
+        "texture_height",
+        [
+            "texture_height1",
+            "texture_height2",
+            "texture_height3",
+            "texture_height4",
+        ],
+    ),
+];
+
+/// Binds the textures to consecutive units and points the `texture_diffuseN`, `texture_specularN`,
+/// ... samplers at them.
+pub(crate) unsafe fn bind_textures(textures: &[Texture], shader: &Shader) {
+    // textures of each type bound so far, the N in texture_diffuseN is one more
+    let mut counts: Vec<(&str, usize)> = Vec::with_capacity(SAMPLER_NAMES.len());
+    for (i, texture) in textures.iter().enumerate() {
+        gl::ActiveTexture(gl::TEXTURE0 + i as u32); // active proper texture unit before binding
+        let number = match counts.iter_mut().find(|(type_, _)| *type_ == texture.type_) {
+            Some((_, count)) => {
+                *count += 1;
+                *count - 1
+            }
+            None => {
+                counts.push((&texture.type_, 1));
+                0
+            }
+        };
+        let name = SAMPLER_NAMES
+            .iter()
+            .find(|(type_, _)| *type_ == texture.type_)
+            .and_then(|(_, names)| names.get(number));
+        // now set the sampler to the correct texture unit, custom types get formatted names
+        match name {
+            Some(name) => shader.set_uniform(name, i as i32),
+            None => shader.set_uniform(&format!("{}{}", texture.type_, number + 1), i as i32),
+        }
+        // and finally bind the texture
+        gl::BindTexture(gl::TEXTURE_2D, texture.id());
+    }
+}
+
+pub(crate) fn vertex_bounds<V: VertexFormat>(vertices: &[V]) -> Bounds {
+    let positions: Vec<_> = vertices.iter().filter_map(V::position).collect();
+    Bounds::from_points(&positions)
+}
+
+/// Sets the attribute pointers of the bound vertex array from `V::layout()`.
+pub(crate) unsafe fn apply_layout<V: VertexFormat>() {
+    let layout = V::layout();
+    debug_assert_eq!(layout.stride, size_of::<V>(), "vertex layout stride");
+    if let Err(e) = layout.validate() {
+        error!("Invalid vertex layout: {}", e);
+    }
+    layout.apply();
+}
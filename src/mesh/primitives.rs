@@ -0,0 +1,290 @@
+//! Procedural meshes, usable without any files on disk.
+//!
+//! Shapes are centered on the origin with +Y up and front faces wound counter-clockwise.
+//! Texture coordinates start at the top left of the image like the OBJ importer, tangents
+//! come from `geometry::generate_tangents`.
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use cgmath::prelude::*;
+use cgmath::{vec2, vec3, Vector3};
+
+use super::Vertex;
+use crate::geometry;
+use crate::model::MeshData;
+
+/// Point of the outline turned around the Y axis by `revolve`, `r` is the distance to the axis.
+#[derive(Clone, Copy)]
+struct ProfilePoint {
+    r: f32,
+    y: f32,
+    /// normal in the (r, y) plane
+    normal_r: f32,
+    normal_y: f32,
+}
+
+impl ProfilePoint {
+    fn new(r: f32, y: f32, normal_r: f32, normal_y: f32) -> Self {
+        ProfilePoint {
+            r,
+            y,
+            normal_r,
+            normal_y,
+        }
+    }
+}
+
+fn finish(mut vertices: Vec<Vertex>, mut indices: Vec<u32>) -> MeshData {
+    geometry::generate_tangents(&mut vertices, &mut indices);
+    MeshData {
+        vertices,
+        indices,
+        textures: Vec::new(),
+    }
+}
+
+/// Two triangles per cell of a grid of `rows` x `columns` vertices stored row after row,
+/// starting at `base`. Rows go down the front face, columns go right.
+/// `skip_row` tells which rows collapse into a point, like the poles of a sphere.
+fn grid_indices(
+    indices: &mut Vec<u32>,
+    base: u32,
+    rows: u32,
+    columns: u32,
+    skip_row: impl Fn(u32) -> bool,
+) {
+    for row in 0..rows.saturating_sub(1) {
+        for column in 0..columns.saturating_sub(1) {
+            let top_left = base + row * columns + column;
+            let bottom_left = top_left + columns;
+            if !skip_row(row) {
+                indices.extend_from_slice(&[top_left, bottom_left, top_left + 1]);
+            }
+            if !skip_row(row + 1) {
+                indices.extend_from_slice(&[top_left + 1, bottom_left, bottom_left + 1]);
+            }
+        }
+    }
+}
+
+/// Turns the profile (from top to bottom on the outside) around the Y axis. U goes once
+/// around, V follows the profile length.
+fn revolve(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    profile: &[ProfilePoint],
+    segments: u32,
+) {
+    let mut lengths = vec![0.0];
+    for pair in profile.windows(2) {
+        let step = vec2(pair[1].r - pair[0].r, pair[1].y - pair[0].y).magnitude();
+        lengths.push(lengths.last().unwrap() + step);
+    }
+    let total = lengths
+        .last()
+        .copied()
+        .unwrap_or_default()
+        .max(f32::EPSILON);
+
+    let base = vertices.len() as u32;
+    for (point, length) in profile.iter().zip(&lengths) {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin, cos) = (u * 2.0 * PI).sin_cos();
+            vertices.push(Vertex {
+                position: vec3(point.r * cos, point.y, -point.r * sin),
+                normal: vec3(point.normal_r * cos, point.normal_y, -point.normal_r * sin)
+                    .normalize(),
+                text_coords: vec2(u, length / total),
+                ..Vertex::default()
+            });
+        }
+    }
+    // sin(PI) is not exactly 0, points close to the axis are collapsed too
+    let extent = profile
+        .iter()
+        .fold(0.0, |extent, point| point.r.abs().max(extent));
+    grid_indices(indices, base, profile.len() as u32, segments + 1, |row| {
+        profile[row as usize].r.abs() <= extent * 1e-5
+    });
+}
+
+/// Flat disc at height `y` facing up or down.
+fn disc(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    radius: f32,
+    y: f32,
+    up: bool,
+    segments: u32,
+) {
+    let normal = if up {
+        Vector3::unit_y()
+    } else {
+        -Vector3::unit_y()
+    };
+    let center = vertices.len() as u32;
+    vertices.push(Vertex {
+        position: vec3(0.0, y, 0.0),
+        normal,
+        text_coords: vec2(0.5, 0.5),
+        ..Vertex::default()
+    });
+    for segment in 0..=segments {
+        let (sin, cos) = (segment as f32 / segments as f32 * 2.0 * PI).sin_cos();
+        vertices.push(Vertex {
+            position: vec3(radius * cos, y, -radius * sin),
+            normal,
+            text_coords: vec2(0.5 + 0.5 * cos, 0.5 - 0.5 * sin),
+            ..Vertex::default()
+        });
+    }
+    for segment in 0..segments {
+        let (a, b) = (center + 1 + segment, center + 2 + segment);
+        if up {
+            indices.extend_from_slice(&[center, a, b]);
+        } else {
+            indices.extend_from_slice(&[center, b, a]);
+        }
+    }
+}
+
+/// Cube with `size` long edges, each face has its own vertices and the whole texture.
+pub fn cube(size: f32) -> MeshData {
+    // normal, right and up of each face seen from the outside
+    let faces: [(Vector3<f32>, Vector3<f32>, Vector3<f32>); 6] = [
+        (Vector3::unit_x(), -Vector3::unit_z(), Vector3::unit_y()),
+        (-Vector3::unit_x(), Vector3::unit_z(), Vector3::unit_y()),
+        (Vector3::unit_y(), Vector3::unit_x(), -Vector3::unit_z()),
+        (-Vector3::unit_y(), Vector3::unit_x(), Vector3::unit_z()),
+        (Vector3::unit_z(), Vector3::unit_x(), Vector3::unit_y()),
+        (-Vector3::unit_z(), -Vector3::unit_x(), Vector3::unit_y()),
+    ];
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (normal, right, up) in faces.iter() {
+        let base = vertices.len() as u32;
+        for (x, y) in corners.iter() {
+            vertices.push(Vertex {
+                position: (normal + right * *x + up * *y) * 0.5 * size,
+                normal: *normal,
+                text_coords: vec2((x + 1.0) * 0.5, (1.0 - y) * 0.5),
+                ..Vertex::default()
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    finish(vertices, indices)
+}
+
+/// Square in the XZ plane facing +Y, split into `subdivisions` x `subdivisions` quads.
+pub fn plane(size: f32, subdivisions: u32) -> MeshData {
+    let cells = subdivisions.max(1);
+    let mut vertices = Vec::with_capacity(((cells + 1) * (cells + 1)) as usize);
+    let mut indices = Vec::with_capacity((cells * cells * 6) as usize);
+    for row in 0..=cells {
+        for column in 0..=cells {
+            let (u, v) = (column as f32 / cells as f32, row as f32 / cells as f32);
+            vertices.push(Vertex {
+                position: vec3((u - 0.5) * size, 0.0, (v - 0.5) * size),
+                normal: Vector3::unit_y(),
+                text_coords: vec2(u, v),
+                ..Vertex::default()
+            });
+        }
+    }
+    grid_indices(&mut indices, 0, cells + 1, cells + 1, |_| false);
+
+    finish(vertices, indices)
+}
+
+/// Sphere made of `segments` slices around Y and `rings` stacks from pole to pole.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let profile: Vec<_> = (0..=rings)
+        .map(|ring| {
+            let (sin, cos) = (ring as f32 / rings as f32 * PI).sin_cos();
+            ProfilePoint::new(radius * sin, radius * cos, sin, cos)
+        })
+        .collect();
+
+    let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    revolve(&mut vertices, &mut indices, &profile, segments);
+    finish(vertices, indices)
+}
+
+/// Closed cylinder along Y.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let half = height * 0.5;
+    let profile = [
+        ProfilePoint::new(radius, half, 1.0, 0.0),
+        ProfilePoint::new(radius, -half, 1.0, 0.0),
+    ];
+
+    let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    revolve(&mut vertices, &mut indices, &profile, segments);
+    disc(&mut vertices, &mut indices, radius, half, true, segments);
+    disc(&mut vertices, &mut indices, radius, -half, false, segments);
+    finish(vertices, indices)
+}
+
+/// Cylinder along Y with hemispheres on both ends, `height` is the length of the
+/// cylindrical part so the whole capsule is `height + 2 * radius` tall.
+/// `rings` is the number of stacks in each hemisphere.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let half = height * 0.5;
+    let mut profile = Vec::with_capacity(2 * (rings as usize + 1));
+    for (center, start) in [(half, 0.0), (-half, FRAC_PI_2)].iter() {
+        for ring in 0..=rings {
+            let angle = start + ring as f32 / rings as f32 * FRAC_PI_2;
+            let (sin, cos) = angle.sin_cos();
+            profile.push(ProfilePoint::new(
+                radius * sin,
+                center + radius * cos,
+                sin,
+                cos,
+            ));
+        }
+    }
+
+    let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    revolve(&mut vertices, &mut indices, &profile, segments);
+    finish(vertices, indices)
+}
+
+/// Cone along Y with the apex on top and a closed base.
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let half = height * 0.5;
+    // perpendicular to the slope
+    let normal = vec2(height, radius).normalize();
+    let profile = [
+        ProfilePoint::new(0.0, half, normal.x, normal.y),
+        ProfilePoint::new(radius, -half, normal.x, normal.y),
+    ];
+
+    let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    revolve(&mut vertices, &mut indices, &profile, segments);
+    disc(&mut vertices, &mut indices, radius, -half, false, segments);
+    finish(vertices, indices)
+}
+
+/// Torus lying in the XZ plane, `radius` goes to the center of the tube.
+pub fn torus(radius: f32, tube_radius: f32, segments: u32, sides: u32) -> MeshData {
+    let (segments, sides) = (segments.max(3), sides.max(3));
+    // around the tube from the top over the outside, clockwise like the other profiles
+    let profile: Vec<_> = (0..=sides)
+        .map(|side| {
+            let (sin, cos) = (side as f32 / sides as f32 * 2.0 * PI).sin_cos();
+            ProfilePoint::new(radius + tube_radius * sin, tube_radius * cos, sin, cos)
+        })
+        .collect();
+
+    let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    revolve(&mut vertices, &mut indices, &profile, segments);
+    finish(vertices, indices)
+}
@@ -2,19 +2,140 @@
 #![allow(dead_code)]
 
 use crate::assets_cache::AssetsCache;
-use crate::mesh::{Mesh, Texture, Vertex};
+use crate::bounds::Bounds;
+use crate::error::{DopplerError, Result};
+use crate::geometry;
+use crate::gltf_loader;
+use crate::gpu::TextureHandle;
+use crate::mesh::{primitives, Mesh, Texture, Vertex};
 use crate::shader::Shader;
-use cgmath::{vec2, vec3};
-use log::{info, warn};
+use crate::utils::{self, ImageData};
+use cgmath::prelude::*;
+use cgmath::{vec2, vec3, vec4, Matrix4};
+use log::{error, info, warn};
 use std::path::Path;
 use tobj;
 
+/// Texture used by a mesh, resolved through `AssetsCache::get_material_texture`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureRef {
+    pub dir: String,
+    /// file name, also used as the cache key
+    pub file: String,
+    pub type_: String,
+}
+
+impl TextureRef {
+    pub fn new(dir: &str, file: &str, type_: &str) -> Self {
+        TextureRef {
+            dir: dir.into(),
+            file: file.into(),
+            type_: type_.into(),
+        }
+    }
+
+    pub fn full_path(&self) -> String {
+        format!("{}/{}", self.dir, self.file)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub textures: Vec<TextureRef>,
+}
+
+/// Node of a glTF scene, places its meshes (stored in mesh-local space) in the model.
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub name: String,
+    /// relative to the parent node
+    pub transform: Matrix4<f32>,
+    /// indices into `Model::meshes`, several nodes can share a mesh
+    pub meshes: Vec<usize>,
+    /// indices into `Model::nodes`
+    pub children: Vec<usize>,
+}
+
+/// CPU side of a model, see `Model::parse`.
+#[derive(Clone, Debug, Default)]
+pub struct ModelData {
+    pub directory: String,
+    pub meshes: Vec<MeshData>,
+    pub nodes: Vec<Node>,
+    pub roots: Vec<usize>,
+    /// images stored inside the model file, decoded together with it
+    pub embedded: Vec<(TextureRef, ImageData)>,
+}
+
+/// Meshes drawn by the node trees under `roots`, with their transform in model space.
+/// Without nodes (OBJ files) every mesh is drawn once as it is.
+pub fn mesh_instances(
+    nodes: &[Node],
+    roots: &[usize],
+    meshes: usize,
+) -> Vec<(usize, Matrix4<f32>)> {
+    if nodes.is_empty() {
+        return (0..meshes)
+            .map(|mesh| (mesh, Matrix4::identity()))
+            .collect();
+    }
+    let mut instances = Vec::new();
+    // glTF node trees are disjoint, `visited` only guards against broken files
+    let mut visited = vec![false; nodes.len()];
+    let mut pending: Vec<(usize, Matrix4<f32>)> = roots
+        .iter()
+        .rev()
+        .map(|&root| (root, Matrix4::identity()))
+        .collect();
+    while let Some((index, parent)) = pending.pop() {
+        if index >= nodes.len() || visited[index] {
+            continue;
+        }
+        visited[index] = true;
+        let node = &nodes[index];
+        let transform = parent * node.transform;
+        instances.extend(
+            node.meshes
+                .iter()
+                .filter(|&&mesh| mesh < meshes)
+                .map(|&mesh| (mesh, transform)),
+        );
+        pending.extend(node.children.iter().rev().map(|&child| (child, transform)));
+    }
+    instances
+}
+
+impl ModelData {
+    /// See `mesh_instances`.
+    pub fn mesh_instances(&self) -> Vec<(usize, Matrix4<f32>)> {
+        mesh_instances(&self.nodes, &self.roots, self.meshes.len())
+    }
+
+    /// Mesh textures that have to be loaded from their own files.
+    pub fn external_textures(&self) -> impl Iterator<Item = &TextureRef> {
+        self.meshes
+            .iter()
+            .flat_map(|mesh| &mesh.textures)
+            .filter(move |texture| !self.embedded.iter().any(|(t, _)| t.file == texture.file))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Model {
     /*  Model Data */
     pub meshes: Vec<Mesh>,
     pub textures_loaded: Vec<Texture>, // stores all the textures loaded so far, optimization to make sure textures aren't loaded more than once.
+    /// node tree of glTF models, empty for OBJ files
+    pub nodes: Vec<Node>,
+    /// indices into `nodes` of the scene root nodes
+    pub roots: Vec<usize>,
+    /// model space bounds of all meshes, see `update_bounds`
+    pub bounds: Bounds,
     directory: String,
+    /// meshes placed by the nodes, see `mesh_instances`
+    instances: Vec<(usize, Matrix4<f32>)>,
 }
 
 impl Default for Model {
@@ -22,93 +143,220 @@ impl Default for Model {
         Model {
             meshes: Vec::new(),
             textures_loaded: Vec::new(),
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            bounds: Bounds::default(),
             directory: "".to_string(),
+            instances: Vec::new(),
         }
     }
 }
 
 impl Model {
-    pub fn new_2d(cache: &mut AssetsCache) -> Model {
-        Self::new_ext("resources/defaults/plane.obj", None, cache, true)
+    /// Untextured 2x2 plane facing +Y, see `AssetsCache::load_2d`.
+    pub fn new_2d(_cache: &mut AssetsCache) -> Model {
+        let mut plane = primitives::plane(2.0, 1);
+        // the image is turned the same way as on the former `resources/defaults/plane.obj`
+        for vertex in &mut plane.vertices {
+            vertex.text_coords = vec2(1.0, 1.0) - vertex.text_coords;
+            vertex.tangent = -vertex.tangent;
+            vertex.bitangent = -vertex.bitangent;
+        }
+
+        let mut model = Model {
+            meshes: vec![Mesh::new(plane.vertices, plane.indices, Vec::new())],
+            ..Model::default()
+        };
+        model.update_bounds();
+        model
     }
 
     /// constructor, expects a filepath to a 3D model.
+    /// Falls back to a placeholder cube when the model cannot be loaded.
     pub fn new_ext(
         path: &str,
         diff_texture: Option<&str>,
         cache: &mut AssetsCache,
         skip_textures: bool,
     ) -> Model {
-        let pathObj = Path::new(path);
-        let mut model = Model {
-            meshes: Vec::<Mesh>::new(),
-            textures_loaded: Vec::<Texture>::new(),
-            directory: pathObj
-                .parent()
-                .unwrap_or_else(|| Path::new(""))
-                .to_str()
-                .unwrap()
-                .into(),
-        };
+        Self::try_new_ext(path, diff_texture, cache, skip_textures).unwrap_or_else(|e| {
+            error!("{}, using placeholder model", e);
+            Self::placeholder()
+        })
+    }
 
-        if pathObj.exists() {
-            model.load_model(path, diff_texture, cache, skip_textures);
-        } else {
-            warn!("{} does not exist, returning empty model", path);
-        }
+    pub fn try_new_ext(
+        path: &str,
+        diff_texture: Option<&str>,
+        cache: &mut AssetsCache,
+        skip_textures: bool,
+    ) -> Result<Model> {
+        let data = Model::parse(path, diff_texture, skip_textures)?;
 
-        model
+        Ok(Model::from_data(data, cache))
     }
 
     pub fn new(path: &str, cache: &mut AssetsCache) -> Model {
         Model::new_ext(path, None, cache, false)
     }
 
-    pub fn Draw(&self, shader: &Shader) {
-        for mesh in &self.meshes {
+    pub fn try_new(path: &str, cache: &mut AssetsCache) -> Result<Model> {
+        Model::try_new_ext(path, None, cache, false)
+    }
+
+    /// Unit cube with a magenta checkerboard texture, stands in for models that failed to load.
+    pub fn placeholder() -> Model {
+        let cube = primitives::cube(1.0);
+        let texture = Texture {
+            handle: TextureHandle::from_raw(unsafe { utils::placeholder_texture() }),
+            type_: "texture_diffuse".into(),
+            path: "placeholder".into(),
+        };
+
+        let mut model = Model {
+            meshes: vec![Mesh::new(cube.vertices, cube.indices, vec![texture])],
+            ..Model::default()
+        };
+        model.update_bounds();
+        model
+    }
+
+    /// Places the meshes with the node tree and updates the union of their bounds, has to
+    /// be called after changing `meshes` or `nodes`.
+    pub fn update_bounds(&mut self) {
+        self.instances = mesh_instances(&self.nodes, &self.roots, self.meshes.len());
+        self.bounds = self
+            .instances
+            .iter()
+            .fold(Bounds::default(), |bounds, (mesh, transform)| {
+                bounds.union(&self.meshes[*mesh].bounds.transform(transform))
+            });
+    }
+
+    /// Draws every mesh instance, setting the `model` uniform to `transform` combined with
+    /// the node transform. The shader has to be bound.
+    pub fn draw_at(&self, shader: &Shader, transform: &Matrix4<f32>) {
+        for (mesh, local) in &self.instances {
+            shader.set_uniform("model", transform * local);
             unsafe {
-                mesh.Draw(shader);
+                self.meshes[*mesh].Draw(shader);
             }
         }
     }
 
-    // loads a model from file and stores the resulting meshes in the meshes vector.
-    fn load_model(
-        &mut self,
-        path: &str,
-        diffuse_path: Option<&str>,
-        cache: &mut AssetsCache,
-        skip_textures: bool,
-    ) {
+    /// Draws the model at the origin, see `draw_at`.
+    pub fn Draw(&self, shader: &Shader) {
+        self.draw_at(shader, &Matrix4::identity());
+    }
+
+    /// Builds the GL meshes from parsed data, textures are looked up in (or loaded into)
+    /// the cache.
+    pub fn from_data(data: ModelData, cache: &mut AssetsCache) -> Model {
+        let mut model = Model {
+            directory: data.directory,
+            nodes: data.nodes,
+            roots: data.roots,
+            ..Model::default()
+        };
+        for (texture, image) in &data.embedded {
+            cache.insert_embedded_texture(texture, image);
+        }
+        for mesh in data.meshes {
+            let textures = mesh
+                .textures
+                .iter()
+                .map(|texture| {
+                    cache.get_material_texture(&texture.dir, &texture.file, &texture.type_)
+                })
+                .collect();
+            model
+                .meshes
+                .push(Mesh::new(mesh.vertices, mesh.indices, textures));
+        }
+        model.update_bounds();
+        info!("Finished loading model");
+
+        model
+    }
+
+    /// Reads the model file without touching GL, safe to call from worker threads.
+    /// glTF files (`.gltf`, `.glb`) are recognized by extension, everything else is read as OBJ.
+    pub fn parse(path: &str, diffuse_path: Option<&str>, skip_textures: bool) -> Result<ModelData> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match extension.as_str() {
+            "gltf" | "glb" => gltf_loader::parse(path, diffuse_path, skip_textures),
+            _ => Self::parse_obj(path, diffuse_path, skip_textures),
+        }
+    }
+
+    fn parse_obj(path: &str, diffuse_path: Option<&str>, skip_textures: bool) -> Result<ModelData> {
+        let path_str = path;
         let path = Path::new(path);
         // println!("Started loading model from path: {}", path.display());
 
         // retrieve the directory path of the filepath
-        self.directory = path
-            .parent()
-            .unwrap_or_else(|| Path::new(""))
-            .to_str()
-            .unwrap()
-            .into();
-        let obj = tobj::load_obj(path, true);
-
-        let (models, materials) = obj.unwrap();
+        let mut data = ModelData {
+            directory: path
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_str()
+                .unwrap_or_default()
+                .into(),
+            ..ModelData::default()
+        };
+        if !path.exists() {
+            return Err(DopplerError::io(
+                path_str,
+                std::io::Error::from(std::io::ErrorKind::NotFound),
+            ));
+        }
+        let (models, materials) =
+            tobj::load_obj(path, true).map_err(|source| DopplerError::Obj {
+                path: path_str.into(),
+                source,
+            })?;
         for model in models {
             let mesh = &model.mesh;
             let num_vertices = mesh.positions.len() / 3;
 
             // data to fill
             let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
-            let indices: Vec<u32> = mesh.indices.clone();
+            let mut indices: Vec<u32> = mesh.indices.clone();
 
             let (p, n, t) = (&mesh.positions, &mesh.normals, &mesh.texcoords);
+            let c = &mesh.vertex_color;
             for i in 0..num_vertices {
-                vertices.push(Vertex {
+                let normal = if n.len() >= (i + 1) * 3 {
+                    vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2])
+                } else {
+                    vec3(0.0, 0.0, 0.0)
+                };
+                let text_coords = if t.len() >= (i + 1) * 2 {
+                    vec2(t[i * 2], 1.0 - t[i * 2 + 1])
+                } else {
+                    vec2(0.0, 0.0)
+                };
+                let mut vertex = Vertex {
                     position: vec3(p[i * 3], p[i * 3 + 1], p[i * 3 + 2]),
-                    normal: vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2]),
-                    text_coords: vec2(t[i * 2], 1.0 - t[i * 2 + 1]),
+                    normal,
+                    text_coords,
                     ..Vertex::default()
-                })
+                };
+                // OBJ colors are RGB only
+                if c.len() >= (i + 1) * 3 {
+                    vertex.color = vec4(c[i * 3], c[i * 3 + 1], c[i * 3 + 2], 1.0);
+                }
+                vertices.push(vertex)
+            }
+            if n.len() < num_vertices * 3 {
+                geometry::generate_normals(&mut vertices, &indices);
+            }
+            if t.len() >= num_vertices * 2 {
+                geometry::generate_tangents(&mut vertices, &mut indices);
             }
 
             // process material
@@ -117,58 +365,51 @@ impl Model {
             if !skip_textures {
                 if let Some(material_id) = mesh.material_id {
                     let material = &materials[material_id];
-
-                    // 1. diffuse map
-                    if !material.diffuse_texture.is_empty() {
-                        let texture = cache.get_material_texture(
-                            &self.directory,
-                            &material.diffuse_texture,
-                            "texture_diffuse",
-                        );
-                        textures.push(texture);
-                    }
-                    // 2. specular map
-                    if !material.specular_texture.is_empty() {
-                        let texture = cache.get_material_texture(
-                            &self.directory,
-                            &material.specular_texture,
-                            "texture_specular",
-                        );
-                        textures.push(texture);
-                    }
-                    // 3. normal map
-                    if !material.normal_texture.is_empty() {
-                        let texture = cache.get_material_texture(
-                            &self.directory,
-                            &material.normal_texture,
-                            "texture_normal",
-                        );
-                        textures.push(texture);
+                    let maps = [
+                        // 1. diffuse map
+                        (&material.diffuse_texture, "texture_diffuse"),
+                        // 2. specular map
+                        (&material.specular_texture, "texture_specular"),
+                        // 3. normal map
+                        (&material.normal_texture, "texture_normal"),
+                    ];
+                    for (file, type_) in maps.iter() {
+                        if !file.is_empty() {
+                            textures.push(TextureRef::new(&data.directory, file, type_));
+                        }
                     }
                 // NOTE: no height maps
-                } else if diffuse_path.is_some() {
-                    let path = diffuse_path.unwrap();
+                } else if let Some(path) = diffuse_path {
                     println!("Loading {}", path);
-                    let dir: String = if path.contains('/') {
-                        Path::new(&path)
-                            .parent()
-                            .unwrap()
-                            .to_str()
-                            .unwrap()
-                            .to_string()
-                    } else {
-                        self.directory.to_string()
-                    };
-                    let texture =
-                        cache.get_material_texture(&dir, diffuse_path.unwrap(), "texture_diffuse");
-                    textures.push(texture);
+                    textures.push(diffuse_override(path, &data.directory));
                 } else {
                     warn!("There are no materials for: {}", path.display());
                 }
             }
 
-            self.meshes.push(Mesh::new(vertices, indices, textures));
+            data.meshes.push(MeshData {
+                vertices,
+                indices,
+                textures,
+            });
         }
-        info!("Finished loading model");
+
+        Ok(data)
     }
 }
+
+/// Diffuse texture passed in place of a material, relative paths point into the model directory.
+pub(crate) fn diffuse_override(path: &str, directory: &str) -> TextureRef {
+    let dir: String = if path.contains('/') {
+        Path::new(&path)
+            .parent()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    } else {
+        directory.to_string()
+    };
+
+    TextureRef::new(&dir, path, "texture_diffuse")
+}
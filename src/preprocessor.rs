@@ -0,0 +1,154 @@
+//! `#include` and `#define` handling for GLSL sources.
+//!
+//! `#include "common/lighting.glsl"` is resolved relative to the including file first
+//! and then relative to the root shader directory. Every file is included at most once,
+//! so shared structs can be pulled in from several places. Defines passed from Rust are
+//! inserted right after the `#version` line of the root shader.
+use crate::error::{DopplerError, Result};
+use std::path::{Path, PathBuf};
+
+/// Where a line of the preprocessed source came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineOrigin {
+    /// index into `Preprocessed::files`, `None` for lines generated from defines
+    pub file: Option<usize>,
+    /// line number in that file, starting at 1
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessed {
+    pub source: String,
+    /// every file that went into `source`, the root shader first
+    pub files: Vec<PathBuf>,
+    /// origin of each line of `source`
+    pub lines: Vec<LineOrigin>,
+}
+
+impl Preprocessed {
+    /// Maps a line of the preprocessed source (starting at 1) back to its file and line.
+    pub fn origin(&self, line: usize) -> Option<(Option<&Path>, usize)> {
+        let origin = self.lines.get(line.checked_sub(1)?)?;
+        let file = origin.file.map(|file| self.files[file].as_path());
+
+        Some((file, origin.line))
+    }
+}
+
+/// Reads and preprocesses shader file.
+pub fn preprocess_file(path: &str, defines: &[(String, String)]) -> Result<Preprocessed> {
+    let source = std::fs::read_to_string(path).map_err(|e| DopplerError::io(path, e))?;
+
+    preprocess(&source, Path::new(path), defines)
+}
+
+/// Preprocesses `source` as if it was read from `path`, includes are read from disk.
+pub fn preprocess(source: &str, path: &Path, defines: &[(String, String)]) -> Result<Preprocessed> {
+    let mut preprocessor = Preprocessor {
+        root_dir: path.parent().unwrap_or_else(|| Path::new("")).to_path_buf(),
+        defines,
+        canonical: Vec::new(),
+        output: Preprocessed::default(),
+    };
+    preprocessor.process(path, source, true)?;
+
+    Ok(preprocessor.output)
+}
+
+struct Preprocessor<'a> {
+    root_dir: PathBuf,
+    defines: &'a [(String, String)],
+    /// canonical paths of `output.files`, used to include every file only once
+    canonical: Vec<PathBuf>,
+    output: Preprocessed,
+}
+
+impl Preprocessor<'_> {
+    fn process(&mut self, path: &Path, source: &str, root: bool) -> Result<()> {
+        let file = self.output.files.len();
+        self.output.files.push(path.to_path_buf());
+        self.canonical.push(canonical(path));
+
+        let has_version = source
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map_or(false, |line| line.trim_start().starts_with("#version"));
+        if root && !has_version {
+            self.push_defines();
+        }
+
+        for (i, line) in source.lines().enumerate() {
+            let number = i + 1;
+            let trimmed = line.trim_start();
+            if let Some(rest) = trimmed.strip_prefix("#include") {
+                let target = include_target(rest).ok_or_else(|| DopplerError::Preprocess {
+                    path: path.display().to_string(),
+                    line: number,
+                    message: format!("malformed include: {}", trimmed),
+                })?;
+                self.include(path, number, target)?;
+                continue;
+            }
+
+            self.push(line, Some(file), number);
+            if root && has_version && trimmed.starts_with("#version") {
+                self.push_defines();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn include(&mut self, from: &Path, line: usize, target: &str) -> Result<()> {
+        let relative = from.parent().unwrap_or_else(|| Path::new("")).join(target);
+        let path = [relative, self.root_dir.join(target)]
+            .iter()
+            .find(|path| path.is_file())
+            .cloned()
+            .ok_or_else(|| DopplerError::Preprocess {
+                path: from.display().to_string(),
+                line,
+                message: format!("cannot find include \"{}\"", target),
+            })?;
+        if self.canonical.contains(&canonical(&path)) {
+            return Ok(());
+        }
+
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| DopplerError::io(&path.display().to_string(), e))?;
+        self.process(&path, &source, false)
+    }
+
+    fn push_defines(&mut self) {
+        for (i, (name, value)) in self.defines.iter().enumerate() {
+            self.push(&format!("#define {} {}", name, value), None, i + 1);
+        }
+    }
+
+    fn push(&mut self, line: &str, file: Option<usize>, number: usize) {
+        self.output.source.push_str(line);
+        self.output.source.push('\n');
+        self.output.lines.push(LineOrigin { file, line: number });
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `"file"` or `<file>` following `#include`
+fn include_target(rest: &str) -> Option<&str> {
+    let rest = rest.trim();
+    let (open, close) = match rest.chars().next()? {
+        '"' => ('"', '"'),
+        '<' => ('<', '>'),
+        _ => return None,
+    };
+    let inner = rest.strip_prefix(open)?;
+    let end = inner.find(close)?;
+    if end == 0 || !inner[end + 1..].trim().is_empty() {
+        return None;
+    }
+
+    Some(&inner[..end])
+}
@@ -1,30 +1,246 @@
 #![allow(non_snake_case)]
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
-use std::fs::File;
-use std::io::Read;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::rc::{Rc, Weak};
 use std::str;
+use std::time::{Duration, Instant, SystemTime};
 
 use gl;
 use gl::types::*;
 
 use crate::consts;
-use cgmath::prelude::*;
-use cgmath::{Matrix, Matrix4, Vector2, Vector3};
+use crate::error::{DopplerError, Result};
+use crate::gpu::{GlKind, GlObject};
+use crate::preprocessor::{self, Preprocessed};
+use crate::uniform::{Uniform, UniformInfo, UniformTable};
+use crate::uniform_buffer;
+use crate::utils::modified;
+use cgmath::{vec3, Matrix4, Vector2, Vector3};
+use log::{error, info, warn};
 
+/// number of shader errors kept for `recent_errors`
+const MAX_RECENT_ERRORS: usize = 16;
+
+thread_local! {
+    static RECENT_ERRORS: RefCell<Vec<ShaderError>> = RefCell::new(Vec::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Geometry,
+    TessControl,
+    TessEvaluation,
+    /// needs GL 4.3, cannot be combined with other stages
+    Compute,
+    /// linking the stages into a program
+    Program,
+}
+
+impl ShaderStage {
+    fn gl_type(self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+            ShaderStage::Program => 0,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::TessControl => "tessellation control",
+            ShaderStage::TessEvaluation => "tessellation evaluation",
+            ShaderStage::Compute => "compute",
+            ShaderStage::Program => "program",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Source line referenced by a driver info log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLine {
+    /// file the line comes from, `None` when it is not known or was generated from defines
+    pub file: Option<String>,
+    /// line number within `file`, starting at 1
+    pub number: usize,
+    pub text: String,
+}
+
+/// Compilation or linking failure reported by the driver.
+#[derive(Debug, Clone)]
+pub struct ShaderError {
+    pub stage: ShaderStage,
+    /// file the failing stage was loaded from
+    pub path: Option<String>,
+    /// driver info log
+    pub log: String,
+    /// source lines referenced by the info log
+    pub lines: Vec<SourceLine>,
+}
+
+impl ShaderError {
+    /// Builds the error from a driver info log, picking the referenced lines out of `source`.
+    pub fn new(stage: ShaderStage, log: String, source: Option<&str>) -> Self {
+        let lines = match source {
+            Some(source) => {
+                let source: Vec<&str> = source.lines().collect();
+                referenced_lines(&log)
+                    .filter_map(|number| {
+                        Some(SourceLine {
+                            file: None,
+                            number,
+                            text: source.get(number.checked_sub(1)?)?.to_string(),
+                        })
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        ShaderError {
+            stage,
+            path: None,
+            log,
+            lines,
+        }
+    }
+
+    /// Same as `new`, with line numbers mapped back through includes to the original files.
+    pub fn from_preprocessed(stage: ShaderStage, log: String, source: &Preprocessed) -> Self {
+        let text: Vec<&str> = source.source.lines().collect();
+        let lines = referenced_lines(&log)
+            .filter_map(|line| {
+                let (file, number) = source.origin(line)?;
+                Some(SourceLine {
+                    file: Some(file.map_or_else(
+                        || "<defines>".to_string(),
+                        |file| file.display().to_string(),
+                    )),
+                    number,
+                    text: text.get(line - 1)?.to_string(),
+                })
+            })
+            .collect();
+
+        ShaderError {
+            stage,
+            path: source.files.first().map(|path| path.display().to_string()),
+            log,
+            lines,
+        }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} shader failed", self.stage)?;
+        if let Some(path) = &self.path {
+            write!(f, " ({})", path)?;
+        }
+        write!(f, ":")?;
+        for line in self.log.lines().filter(|line| !line.trim().is_empty()) {
+            write!(f, "\n{}", line)?;
+        }
+        for line in &self.lines {
+            match &line.file {
+                Some(file) => write!(f, "\n{}:{} | {}", file, line.number, line.text)?,
+                None => write!(f, "\n{:>5} | {}", line.number, line.text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Parses the source line out of an info log line. Handles the common driver formats:
+/// `0:12(5): error` (Mesa), `0(12) : error` (Nvidia) and `ERROR: 0:12: ...` (AMD, Intel).
+fn error_line(log_line: &str) -> Option<usize> {
+    let line = log_line.trim_start();
+    let line = ["ERROR:", "WARNING:"]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))
+        .unwrap_or(line)
+        .trim_start();
+    let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() == line.len() {
+        return None;
+    }
+    let rest = rest.strip_prefix(':').or_else(|| rest.strip_prefix('('))?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    digits.parse().ok()
+}
+
+/// Sorted, deduplicated source lines mentioned in the info log.
+fn referenced_lines(log: &str) -> impl Iterator<Item = usize> {
+    let mut numbers: Vec<usize> = log.lines().filter_map(error_line).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    numbers.into_iter()
+}
+
+fn record_error(e: &ShaderError) {
+    RECENT_ERRORS.with(|errors| {
+        let mut errors = errors.borrow_mut();
+        if errors.len() == MAX_RECENT_ERRORS {
+            errors.remove(0);
+        }
+        errors.push(e.clone());
+    });
+}
+
+/// Shader errors from this thread, oldest first. Meant for tooling and the debug layer.
+pub fn recent_errors() -> Vec<ShaderError> {
+    RECENT_ERRORS.with(|errors| errors.borrow().clone())
+}
+
+pub fn clear_recent_errors() {
+    RECENT_ERRORS.with(|errors| errors.borrow_mut().clear());
+}
+
+/// GL program with its active uniforms, introspected after linking. The program is
+/// deleted when the last shader using it is dropped or it gets replaced by a reload.
 #[derive(Debug)]
-pub struct Shader {
-    pub id: u32,
+struct Program {
+    object: GlObject,
+    uniforms: UniformTable,
 }
 
-impl Drop for Shader {
-    fn drop(&mut self) {
+impl Program {
+    fn new(id: u32) -> Self {
         unsafe {
-            gl::DeleteShader(self.id);
+            uniform_buffer::bind_blocks(id);
+            Program {
+                object: GlObject::from_raw(GlKind::Program, id),
+                uniforms: UniformTable::introspect(id),
+            }
         }
     }
 }
 
+/// Linked GL program. Shaders created from files share their program with the hot
+/// reload registry, which swaps it when the sources change on disk (see `poll_changes`).
+#[derive(Debug)]
+pub struct Shader {
+    program: Rc<RefCell<Program>>,
+}
+
 impl Default for Shader {
     fn default() -> Self {
         let vShaderCode = CString::new(consts::VERTEX_SHADER_SRC.as_bytes()).unwrap();
@@ -38,205 +254,667 @@ impl Default for Shader {
 /// a few more setters for uniforms)
 #[allow(dead_code)]
 impl Shader {
+    fn from_program(id: u32) -> Shader {
+        Shader {
+            program: Rc::new(RefCell::new(Program::new(id))),
+        }
+    }
+
+    /// current GL program id, changes when the shader gets hot reloaded
+    pub fn id(&self) -> u32 {
+        self.program.borrow().object.id()
+    }
+
+    /// Active uniform with the given name, e.g. `pointLights[0].position`.
+    pub fn uniform(&self, name: &str) -> Option<UniformInfo> {
+        self.program.borrow().uniforms.get(name)
+    }
+
+    /// Names of all active uniforms, sorted.
+    pub fn uniform_names(&self) -> Vec<String> {
+        self.program.borrow().uniforms.names()
+    }
+
+    /// Sets the uniform, using the location table built after linking. The program has to
+    /// be bound with `use_program` first, debug builds check it.
+    /// Unknown names and values of a wrong type are logged once per name and ignored.
+    pub fn set_uniform<T: Uniform>(&self, name: &str, value: T) {
+        let mut program = self.program.borrow_mut();
+        let id = program.object.id();
+        if let Some(location) = program.uniforms.location::<T>(id, name) {
+            unsafe {
+                debug_assert_eq!(
+                    current_program(),
+                    id,
+                    "set_uniform(\"{}\") without use_program",
+                    name
+                );
+                value.apply(location);
+            }
+        }
+    }
+
+    /// Compiles shader from sources, errors are logged and replaced with the placeholder shader.
     pub fn new(vShaderCode: CString, fShaderCode: CString) -> Shader {
-        let mut shader = Shader { id: 0 };
+        Self::try_new(vShaderCode, fShaderCode).unwrap_or_else(|e| {
+            error!("Using placeholder shader, {}", e);
+            Self::placeholder()
+        })
+    }
 
-        // 2. compile shaders
-        unsafe {
-            // vertex shader
-            let vertex = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(vertex, 1, &vShaderCode.as_ptr(), ptr::null());
-            gl::CompileShader(vertex);
-            shader.check_compile_errors(vertex, "VERTEX");
-            // fragment Shader
-            let fragment = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(fragment, 1, &fShaderCode.as_ptr(), ptr::null());
-            gl::CompileShader(fragment);
-            shader.check_compile_errors(fragment, "FRAGMENT");
-            // shader Program
-            let ID = gl::CreateProgram();
-            gl::AttachShader(ID, vertex);
-            gl::AttachShader(ID, fragment);
-            gl::LinkProgram(ID);
-            shader.check_compile_errors(ID, "PROGRAM");
-            // delete the shaders as they're linked into our program now and no longer necessary
-            gl::DeleteShader(vertex);
-            gl::DeleteShader(fragment);
-            shader.id = ID;
-        }
-
-        shader
+    pub fn try_new(
+        vShaderCode: CString,
+        fShaderCode: CString,
+    ) -> std::result::Result<Shader, ShaderError> {
+        let id = unsafe {
+            compile_program(&[
+                (ShaderStage::Vertex, &vShaderCode),
+                (ShaderStage::Fragment, &fShaderCode),
+            ])?
+        };
+
+        Ok(Shader::from_program(id))
     }
 
+    /// Loads shader from files, falling back to a magenta placeholder when they cannot be
+    /// read or compiled. Sources are preprocessed, see `preprocessor` for `#include` support.
     pub fn from_file(vertexPath: &str, fragmentPath: &str) -> Shader {
-        // 1. retrieve the vertex/fragment source code from filesystem
-        let mut vShaderFile =
-            File::open(vertexPath).unwrap_or_else(|_| panic!("Failed to open {}", vertexPath));
-        let mut fShaderFile =
-            File::open(fragmentPath).unwrap_or_else(|_| panic!("Failed to open {}", fragmentPath));
-        let mut vertexCode = String::new();
-        let mut fragmentCode = String::new();
-        vShaderFile
-            .read_to_string(&mut vertexCode)
-            .expect("Failed to read vertex shader");
-        fShaderFile
-            .read_to_string(&mut fragmentCode)
-            .expect("Failed to read fragment shader");
-
-        let vShaderCode = CString::new(vertexCode.as_bytes()).unwrap();
-        let fShaderCode = CString::new(fragmentCode.as_bytes()).unwrap();
+        Self::from_file_with_defines(vertexPath, fragmentPath, &[])
+    }
 
-        Shader::new(vShaderCode, fShaderCode)
+    pub fn try_from_file(vertexPath: &str, fragmentPath: &str) -> Result<Shader> {
+        Self::try_from_file_with_defines(vertexPath, fragmentPath, &[])
+    }
+
+    /// Same as `from_file`, `defines` are injected after the `#version` line of both stages.
+    pub fn from_file_with_defines(
+        vertexPath: &str,
+        fragmentPath: &str,
+        defines: &[(&str, &str)],
+    ) -> Shader {
+        ShaderBuilder::new()
+            .vertex_file(vertexPath)
+            .fragment_file(fragmentPath)
+            .defines(defines)
+            .build_or_placeholder()
+    }
+
+    pub fn try_from_file_with_defines(
+        vertexPath: &str,
+        fragmentPath: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Shader> {
+        ShaderBuilder::new()
+            .vertex_file(vertexPath)
+            .fragment_file(fragmentPath)
+            .defines(defines)
+            .build()
+    }
+
+    /// Plain magenta shader used in place of shaders that failed to load.
+    pub fn placeholder() -> Shader {
+        let vShaderCode = CString::new(consts::PLACEHOLDER_VERTEX_SRC.as_bytes()).unwrap();
+        let fShaderCode = CString::new(consts::PLACEHOLDER_FRAGMENT_SRC.as_bytes()).unwrap();
+
+        Self::try_new(vShaderCode, fShaderCode).unwrap_or_else(|e| {
+            error!("{}", e);
+            Shader::from_program(0)
+        })
     }
 
     /// activate the shader
     /// ------------------------------------------------------------------------
     pub unsafe fn use_program(&self) {
-        gl::UseProgram(self.id)
+        gl::UseProgram(self.id())
     }
 
-    /// utility uniform functions
+    /// Runs a compute program with the given number of work groups. Writes are not visible
+    /// to later draws or dispatches until a matching `gl::MemoryBarrier`.
+    pub unsafe fn dispatch(&self, x: u32, y: u32, z: u32) {
+        gl::UseProgram(self.id());
+        gl::DispatchCompute(x, y, z);
+    }
+
+    /// utility uniform functions, prefer `set_uniform`
     /// ------------------------------------------------------------------------
     pub unsafe fn setBool(&self, name: &CStr, value: bool) {
-        gl::Uniform1i(gl::GetUniformLocation(self.id, name.as_ptr()), value as i32);
+        self.set_uniform(&name.to_string_lossy(), value);
     }
     /// ------------------------------------------------------------------------
     pub unsafe fn setInt(&self, name: &CStr, value: i32) {
-        gl::Uniform1i(gl::GetUniformLocation(self.id, name.as_ptr()), value);
+        self.set_uniform(&name.to_string_lossy(), value);
     }
     /// ------------------------------------------------------------------------
     pub unsafe fn setFloat(&self, name: &CStr, value: f32) {
-        gl::Uniform1f(gl::GetUniformLocation(self.id, name.as_ptr()), value);
+        self.set_uniform(&name.to_string_lossy(), value);
     }
     /// ------------------------------------------------------------------------
     pub unsafe fn set_vector3(&self, name: &CStr, value: &Vector3<f32>) {
-        gl::Uniform3fv(
-            gl::GetUniformLocation(self.id, name.as_ptr()),
-            1,
-            value.as_ptr(),
-        );
+        self.set_uniform(&name.to_string_lossy(), value);
     }
     pub unsafe fn set_vector2(&self, name: &CStr, value: &Vector2<f32>) {
-        gl::Uniform2fv(
-            gl::GetUniformLocation(self.id, name.as_ptr()),
-            1,
-            value.as_ptr(),
-        );
+        self.set_uniform(&name.to_string_lossy(), value);
     }
     /// ------------------------------------------------------------------------
     pub unsafe fn set_vec3(&self, name: &CStr, x: f32, y: f32, z: f32) {
-        gl::Uniform3f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z);
+        self.set_uniform(&name.to_string_lossy(), vec3(x, y, z));
     }
     /// ------------------------------------------------------------------------
     pub unsafe fn set_mat4(&self, name: &CStr, mat: &Matrix4<f32>) {
-        gl::UniformMatrix4fv(
-            gl::GetUniformLocation(self.id, name.as_ptr()),
-            1,
-            gl::FALSE,
-            mat.as_ptr(),
+        self.set_uniform(&name.to_string_lossy(), mat);
+    }
+
+    /// Only used in 4.9 Geometry shaders - ignore until then (shader.h in original C++)
+    pub fn with_geometry_shader(vertexPath: &str, fragmentPath: &str, geometryPath: &str) -> Self {
+        ShaderBuilder::new()
+            .vertex_file(vertexPath)
+            .fragment_file(fragmentPath)
+            .geometry_file(geometryPath)
+            .build_or_placeholder()
+    }
+
+    pub fn try_with_geometry_shader(
+        vertexPath: &str,
+        fragmentPath: &str,
+        geometryPath: &str,
+    ) -> Result<Self> {
+        ShaderBuilder::new()
+            .vertex_file(vertexPath)
+            .fragment_file(fragmentPath)
+            .geometry_file(geometryPath)
+            .build()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum StageSource {
+    Code(String),
+    File(String),
+}
+
+/// Builds a program from any set of stages, given as source strings or files. Sources are
+/// preprocessed, includes in source strings are resolved relative to the working directory.
+/// Programs with at least one file stage are hot reloaded like `Shader::from_file`.
+///
+/// Compute programs consist of a single `Compute` stage and are run with `Shader::dispatch`.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderBuilder {
+    stages: Vec<(ShaderStage, StageSource)>,
+    defines: Vec<(String, String)>,
+}
+
+impl ShaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_stage(mut self, stage: ShaderStage, source: StageSource) -> Self {
+        self.stages.retain(|(existing, _)| *existing != stage);
+        self.stages.push((stage, source));
+        self
+    }
+
+    /// Sets the source of `stage`, replacing a previously set one.
+    pub fn stage(self, stage: ShaderStage, source: &str) -> Self {
+        self.with_stage(stage, StageSource::Code(source.to_string()))
+    }
+
+    /// Loads `stage` from a file, replacing a previously set source.
+    pub fn stage_file(self, stage: ShaderStage, path: &str) -> Self {
+        self.with_stage(stage, StageSource::File(path.to_string()))
+    }
+
+    pub fn vertex(self, source: &str) -> Self {
+        self.stage(ShaderStage::Vertex, source)
+    }
+
+    pub fn vertex_file(self, path: &str) -> Self {
+        self.stage_file(ShaderStage::Vertex, path)
+    }
+
+    pub fn fragment(self, source: &str) -> Self {
+        self.stage(ShaderStage::Fragment, source)
+    }
+
+    pub fn fragment_file(self, path: &str) -> Self {
+        self.stage_file(ShaderStage::Fragment, path)
+    }
+
+    pub fn geometry(self, source: &str) -> Self {
+        self.stage(ShaderStage::Geometry, source)
+    }
+
+    pub fn geometry_file(self, path: &str) -> Self {
+        self.stage_file(ShaderStage::Geometry, path)
+    }
+
+    pub fn tess_control(self, source: &str) -> Self {
+        self.stage(ShaderStage::TessControl, source)
+    }
+
+    pub fn tess_control_file(self, path: &str) -> Self {
+        self.stage_file(ShaderStage::TessControl, path)
+    }
+
+    pub fn tess_evaluation(self, source: &str) -> Self {
+        self.stage(ShaderStage::TessEvaluation, source)
+    }
+
+    pub fn tess_evaluation_file(self, path: &str) -> Self {
+        self.stage_file(ShaderStage::TessEvaluation, path)
+    }
+
+    pub fn compute(self, source: &str) -> Self {
+        self.stage(ShaderStage::Compute, source)
+    }
+
+    pub fn compute_file(self, path: &str) -> Self {
+        self.stage_file(ShaderStage::Compute, path)
+    }
+
+    /// Injected after the `#version` line of every stage.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn defines(self, defines: &[(&str, &str)]) -> Self {
+        defines
+            .iter()
+            .fold(self, |builder, (name, value)| builder.define(name, value))
+    }
+
+    /// Checks that the stages form a valid program before anything gets compiled.
+    pub fn validate(&self) -> std::result::Result<(), ShaderError> {
+        let has = |stage| self.stages.iter().any(|(existing, _)| *existing == stage);
+        let problem = if self.stages.is_empty() {
+            Some("no shader stages")
+        } else if has(ShaderStage::Program) {
+            Some("program is not a shader stage")
+        } else if has(ShaderStage::Compute) && self.stages.len() > 1 {
+            Some("compute shaders cannot be combined with other stages")
+        } else if !has(ShaderStage::Compute) && !has(ShaderStage::Vertex) {
+            Some("missing vertex stage")
+        } else if has(ShaderStage::TessControl) && !has(ShaderStage::TessEvaluation) {
+            Some("tessellation control stage without tessellation evaluation stage")
+        } else {
+            None
+        };
+
+        match problem {
+            Some(problem) => Err(ShaderError {
+                path: self
+                    .file_paths()
+                    .first()
+                    .map(|path| path.display().to_string()),
+                ..ShaderError::new(ShaderStage::Program, problem.to_string(), None)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Compiles and links the program.
+    pub fn build(&self) -> Result<Shader> {
+        self.validate().map_err(|e| {
+            record_error(&e);
+            DopplerError::Shader(e)
+        })?;
+        let sources = self.preprocess()?;
+        let id = unsafe { compile_preprocessed(&sources)? };
+        let shader = Shader::from_program(id);
+        if !self.file_paths().is_empty() {
+            watch(&shader, self.clone(), included_paths(&sources));
+        }
+
+        Ok(shader)
+    }
+
+    /// Same as `build`, errors are logged and replaced with the placeholder shader.
+    pub fn build_or_placeholder(&self) -> Shader {
+        self.build().unwrap_or_else(|e| {
+            error!("Using placeholder shader, {}", e);
+            let shader = Shader::placeholder();
+            let paths = self.file_paths();
+            // keep watching the files, fixing them replaces the placeholder
+            if !paths.is_empty() {
+                watch(&shader, self.clone(), paths);
+            }
+            shader
+        })
+    }
+
+    fn preprocess(&self) -> Result<Vec<(ShaderStage, Preprocessed)>> {
+        self.stages
+            .iter()
+            .map(|(stage, source)| {
+                let source = match source {
+                    StageSource::File(path) => preprocessor::preprocess_file(path, &self.defines)?,
+                    StageSource::Code(code) => {
+                        let name = format!("<{}>", stage);
+                        preprocessor::preprocess(code, Path::new(&name), &self.defines)?
+                    }
+                };
+                Ok((*stage, source))
+            })
+            .collect()
+    }
+
+    fn file_paths(&self) -> Vec<PathBuf> {
+        self.stages
+            .iter()
+            .filter_map(|(_, source)| match source {
+                StageSource::File(path) => Some(PathBuf::from(path)),
+                StageSource::Code(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// All files that went into the sources, includes too.
+fn included_paths(sources: &[(ShaderStage, Preprocessed)]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for path in sources.iter().flat_map(|(_, source)| source.files.iter()) {
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+
+    paths
+}
+
+struct WatchedShader {
+    program: Weak<RefCell<Program>>,
+    files: ShaderBuilder,
+    /// files checked for changes, including the ones pulled in with `#include`
+    paths: Vec<PathBuf>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl WatchedShader {
+    unsafe fn reload_if_changed(&mut self) -> bool {
+        let modified: Vec<_> = self.paths.iter().map(|path| modified(path)).collect();
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+
+        self.reload()
+    }
+
+    /// Recompiles the sources, the previous program is kept when that fails.
+    unsafe fn reload(&mut self) -> bool {
+        let program = match self.program.upgrade() {
+            Some(program) => program,
+            None => return false,
+        };
+        let sources = match self.files.preprocess() {
+            Ok(sources) => sources,
+            Err(e) => {
+                warn!("Cannot reload shader, {}", e);
+                return false;
+            }
+        };
+        // includes might have changed
+        self.paths = included_paths(&sources);
+        self.modified = self.paths.iter().map(|path| modified(path)).collect();
+
+        match compile_preprocessed(&sources) {
+            Ok(id) => {
+                let mut program = program.borrow_mut();
+                copy_uniforms(program.object.id(), id);
+                // drops the previous program
+                *program = Program::new(id);
+                let names: Vec<String> = self
+                    .files
+                    .file_paths()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                info!("Reloaded shader {}", names.join(", "));
+                true
+            }
+            Err(e) => {
+                error!("Keeping previous shader, {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    watched: Vec<WatchedShader>,
+    last_poll: Option<Instant>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+fn watch(shader: &Shader, files: ShaderBuilder, paths: Vec<PathBuf>) {
+    let modified = paths.iter().map(|path| modified(path)).collect();
+    REGISTRY.with(|registry| {
+        registry.borrow_mut().watched.push(WatchedShader {
+            program: Rc::downgrade(&shader.program),
+            files,
+            paths,
+            modified,
+        })
+    });
+}
+
+/// Recompiles shaders created from files whose sources changed on disk. Files are checked
+/// at most every `consts::SHADER_POLL_INTERVAL` ms, returns the number of reloaded shaders.
+pub fn poll_changes() -> usize {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        let now = Instant::now();
+        let interval = Duration::from_millis(consts::SHADER_POLL_INTERVAL);
+        if matches!(registry.last_poll, Some(last) if now.duration_since(last) < interval) {
+            return 0;
+        }
+        registry.last_poll = Some(now);
+        registry
+            .watched
+            .retain(|watched| watched.program.strong_count() > 0);
+
+        registry
+            .watched
+            .iter_mut()
+            .map(|watched| unsafe { watched.reload_if_changed() })
+            .filter(|reloaded| *reloaded)
+            .count()
+    })
+}
+
+/// Recompiles all shaders created from files, regardless of modification times.
+pub fn reload_all() -> usize {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry
+            .watched
+            .retain(|watched| watched.program.strong_count() > 0);
+
+        registry
+            .watched
+            .iter_mut()
+            .map(|watched| unsafe { watched.reload() })
+            .filter(|reloaded| *reloaded)
+            .count()
+    })
+}
+
+/// Program bound with `gl::UseProgram`.
+unsafe fn current_program() -> u32 {
+    let mut current = 0;
+    gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut current);
+    current as u32
+}
+
+/// Carries uniform values over to a reloaded program, so values set once after creation
+/// (samplers, screen size, ...) survive the reload.
+unsafe fn copy_uniforms(from: u32, to: u32) {
+    let current = current_program();
+    gl::UseProgram(to);
+
+    let mut count = 0;
+    gl::GetProgramiv(from, gl::ACTIVE_UNIFORMS, &mut count);
+    let mut name = [0u8; 256];
+    for i in 0..count.max(0) as u32 {
+        let (mut length, mut size, mut type_) = (0, 0, 0);
+        gl::GetActiveUniform(
+            from,
+            i,
+            name.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut type_,
+            name.as_mut_ptr() as *mut GLchar,
+        );
+        let name = name.as_ptr() as *const GLchar;
+        let (source, target) = (
+            gl::GetUniformLocation(from, name),
+            gl::GetUniformLocation(to, name),
         );
+        if source < 0 || target < 0 {
+            continue;
+        }
+
+        let mut floats = [0.0f32; 16];
+        let mut ints = [0i32; 1];
+        match type_ {
+            gl::FLOAT | gl::FLOAT_VEC2 | gl::FLOAT_VEC3 | gl::FLOAT_VEC4 => {
+                gl::GetUniformfv(from, source, floats.as_mut_ptr());
+                match type_ {
+                    gl::FLOAT => gl::Uniform1fv(target, 1, floats.as_ptr()),
+                    gl::FLOAT_VEC2 => gl::Uniform2fv(target, 1, floats.as_ptr()),
+                    gl::FLOAT_VEC3 => gl::Uniform3fv(target, 1, floats.as_ptr()),
+                    _ => gl::Uniform4fv(target, 1, floats.as_ptr()),
+                }
+            }
+            gl::FLOAT_MAT3 => {
+                gl::GetUniformfv(from, source, floats.as_mut_ptr());
+                gl::UniformMatrix3fv(target, 1, gl::FALSE, floats.as_ptr());
+            }
+            gl::FLOAT_MAT4 => {
+                gl::GetUniformfv(from, source, floats.as_mut_ptr());
+                gl::UniformMatrix4fv(target, 1, gl::FALSE, floats.as_ptr());
+            }
+            gl::INT | gl::BOOL | gl::SAMPLER_2D | gl::SAMPLER_CUBE => {
+                gl::GetUniformiv(from, source, ints.as_mut_ptr());
+                gl::Uniform1iv(target, 1, ints.as_ptr());
+            }
+            _ => (),
+        }
     }
 
-    /// utility function for checking shader compilation/linking errors.
-    /// ------------------------------------------------------------------------
-    unsafe fn check_compile_errors(&self, shader: u32, type_: &str) {
+    gl::UseProgram(current);
+}
+
+/// Compiles and links the given stages, failures are recorded for `recent_errors`.
+unsafe fn compile_program(
+    stages: &[(ShaderStage, &CStr)],
+) -> std::result::Result<u32, ShaderError> {
+    let result = try_compile_program(stages);
+    if let Err(e) = &result {
+        record_error(e);
+    }
+
+    result
+}
+
+/// Compiles preprocessed stages, error lines are mapped back to the original files.
+unsafe fn compile_preprocessed(sources: &[(ShaderStage, Preprocessed)]) -> Result<u32> {
+    let mut stages = Vec::with_capacity(sources.len());
+    for (stage, source) in sources {
+        let code = CString::new(source.source.as_bytes()).map_err(|e| {
+            DopplerError::io(
+                &source.files[0].display().to_string(),
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            )
+        })?;
+        stages.push((*stage, code));
+    }
+    let stage_refs: Vec<(ShaderStage, &CStr)> = stages
+        .iter()
+        .map(|(stage, code)| (*stage, code.as_c_str()))
+        .collect();
+
+    try_compile_program(&stage_refs).map_err(|e| {
+        let e = match sources.iter().find(|(stage, _)| *stage == e.stage) {
+            Some((_, source)) => ShaderError::from_preprocessed(e.stage, e.log, source),
+            None => e,
+        };
+        record_error(&e);
+        DopplerError::Shader(e)
+    })
+}
+
+unsafe fn try_compile_program(
+    stages: &[(ShaderStage, &CStr)],
+) -> std::result::Result<u32, ShaderError> {
+    // 2. compile shaders
+    let mut compiled = Vec::with_capacity(stages.len());
+    for (stage, source) in stages {
+        let shader = gl::CreateShader(stage.gl_type());
+        gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
         let mut is_success = gl::FALSE as GLint;
-        let mut infoLog = Vec::with_capacity(1024);
-        infoLog.set_len(1024 - 1); // subtract 1 to skip the trailing null character
-        if type_ != "PROGRAM" {
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut is_success);
-            if is_success != gl::TRUE as GLint {
-                gl::GetShaderInfoLog(
-                    shader,
-                    1024,
-                    ptr::null_mut(),
-                    infoLog.as_mut_ptr() as *mut GLchar,
-                );
-                println!(
-                    "ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                    type_,
-                    str::from_utf8(&infoLog).unwrap()
-                );
-            }
-        } else {
-            gl::GetProgramiv(shader, gl::LINK_STATUS, &mut is_success);
-            if is_success != gl::TRUE as GLint {
-                gl::GetProgramInfoLog(
-                    shader,
-                    1024,
-                    ptr::null_mut(),
-                    infoLog.as_mut_ptr() as *mut GLchar,
-                );
-                println!(
-                    "ERROR::PROGRAM_LINKING_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                    type_,
-                    str::from_utf8(&infoLog).unwrap()
-                );
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut is_success);
+        if is_success != gl::TRUE as GLint {
+            let log = info_log(shader, gl::GetShaderiv, gl::GetShaderInfoLog);
+            gl::DeleteShader(shader);
+            for shader in compiled {
+                gl::DeleteShader(shader);
             }
+            return Err(ShaderError::new(
+                *stage,
+                log,
+                Some(&source.to_string_lossy()),
+            ));
         }
+        compiled.push(shader);
     }
 
-    /// Only used in 4.9 Geometry shaders - ignore until then (shader.h in original C++)
-    pub fn with_geometry_shader(vertexPath: &str, fragmentPath: &str, geometryPath: &str) -> Self {
-        let mut shader = Shader { id: 0 };
-        // 1. retrieve the vertex/fragment source code from filesystem
-        let mut vShaderFile =
-            File::open(vertexPath).unwrap_or_else(|_| panic!("Failed to open {}", vertexPath));
-        let mut fShaderFile =
-            File::open(fragmentPath).unwrap_or_else(|_| panic!("Failed to open {}", fragmentPath));
-        let mut gShaderFile =
-            File::open(geometryPath).unwrap_or_else(|_| panic!("Failed to open {}", geometryPath));
-        let mut vertexCode = String::new();
-        let mut fragmentCode = String::new();
-        let mut geometryCode = String::new();
-        vShaderFile
-            .read_to_string(&mut vertexCode)
-            .expect("Failed to read vertex shader");
-        fShaderFile
-            .read_to_string(&mut fragmentCode)
-            .expect("Failed to read fragment shader");
-        gShaderFile
-            .read_to_string(&mut geometryCode)
-            .expect("Failed to read geometry shader");
-
-        let vShaderCode = CString::new(vertexCode.as_bytes()).unwrap();
-        let fShaderCode = CString::new(fragmentCode.as_bytes()).unwrap();
-        let gShaderCode = CString::new(geometryCode.as_bytes()).unwrap();
-
-        // 2. compile shaders
-        unsafe {
-            // vertex shader
-            let vertex = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(vertex, 1, &vShaderCode.as_ptr(), ptr::null());
-            gl::CompileShader(vertex);
-            shader.check_compile_errors(vertex, "VERTEX");
-            // fragment Shader
-            let fragment = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(fragment, 1, &fShaderCode.as_ptr(), ptr::null());
-            gl::CompileShader(fragment);
-            shader.check_compile_errors(fragment, "FRAGMENT");
-            // geometry shader
-            let geometry = gl::CreateShader(gl::GEOMETRY_SHADER);
-            gl::ShaderSource(geometry, 1, &gShaderCode.as_ptr(), ptr::null());
-            gl::CompileShader(geometry);
-            shader.check_compile_errors(geometry, "GEOMETRY");
-
-            // shader Program
-            let ID = gl::CreateProgram();
-            gl::AttachShader(ID, vertex);
-            gl::AttachShader(ID, fragment);
-            gl::AttachShader(ID, geometry);
-            gl::LinkProgram(ID);
-            shader.check_compile_errors(ID, "PROGRAM");
-            // delete the shaders as they're linked into our program now and no longer necessary
-            gl::DeleteShader(vertex);
-            gl::DeleteShader(fragment);
-            gl::DeleteShader(geometry);
-            shader.id = ID;
-        }
-
-        shader
+    // shader Program
+    let ID = gl::CreateProgram();
+    for shader in &compiled {
+        gl::AttachShader(ID, *shader);
+    }
+    gl::LinkProgram(ID);
+    // delete the shaders as they're linked into our program now and no longer necessary
+    for shader in compiled {
+        gl::DeleteShader(shader);
     }
+
+    let mut is_success = gl::FALSE as GLint;
+    gl::GetProgramiv(ID, gl::LINK_STATUS, &mut is_success);
+    if is_success != gl::TRUE as GLint {
+        let log = info_log(ID, gl::GetProgramiv, gl::GetProgramInfoLog);
+        gl::DeleteProgram(ID);
+        return Err(ShaderError::new(ShaderStage::Program, log, None));
+    }
+
+    Ok(ID)
+}
+
+unsafe fn info_log(
+    object: u32,
+    get_iv: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut length = 0;
+    get_iv(object, gl::INFO_LOG_LENGTH, &mut length);
+    let mut log = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    get_log(
+        object,
+        log.len() as GLsizei,
+        &mut written,
+        log.as_mut_ptr() as *mut GLchar,
+    );
+    log.truncate(written.max(0) as usize);
+
+    String::from_utf8_lossy(&log).into_owned()
 }
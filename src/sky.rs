@@ -1,94 +1,90 @@
+use crate::error::Result;
+use crate::gpu::{GlKind, GlObject};
+use crate::mesh::{primitives, Mesh};
 use crate::shader::*;
 use crate::utils::*;
-use cgmath::Matrix4;
-use gl::types::*;
-use std::mem;
-use std::os::raw::c_void;
-use std::ptr;
+use serde::{Deserialize, Serialize};
 
-pub struct Sky {
-    shader: Shader,
-    texture_id: u32,
-    vao: u32,
-    vbo: u32,
+/// Cubemap faces in order: right, left, top, bottom, back, front.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SkyConfig {
+    pub faces: [String; 6],
 }
 
-impl Drop for Sky {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &self.vao);
-            gl::DeleteBuffers(1, &self.vbo);
+impl Default for SkyConfig {
+    fn default() -> Self {
+        SkyConfig {
+            faces: [
+                "resources/objects/skybox/right.jpg".into(),
+                "resources/objects/skybox/left.jpg".into(),
+                "resources/objects/skybox/top.jpg".into(),
+                "resources/objects/skybox/bottom.jpg".into(),
+                "resources/objects/skybox/back.jpg".into(),
+                "resources/objects/skybox/front.jpg".into(),
+            ],
         }
     }
 }
 
+pub struct Sky {
+    pub config: SkyConfig,
+    shader: Shader,
+    texture: GlObject,
+    cube: Mesh,
+}
+
 impl Sky {
     pub unsafe fn new() -> Sky {
+        Self::from_config(SkyConfig::default())
+    }
+
+    pub unsafe fn try_new() -> Result<Sky> {
+        Self::try_from_config(SkyConfig::default())
+    }
+
+    /// Missing shaders or faces are replaced with placeholders.
+    pub unsafe fn from_config(config: SkyConfig) -> Sky {
         let shader =
             Shader::from_file("resources/shaders/skybox.vs", "resources/shaders/skybox.fs");
+        let faces: Vec<&str> = config.faces.iter().map(|face| face.as_str()).collect();
+        let cubemap_texture = load_cubemap(&faces);
+
+        Self::with_resources(config, shader, cubemap_texture)
+    }
 
-        let skybox_vertices: [f32; 108] = [
-            // positions
-            -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0,
-            -1.0, 1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, -1.0,
-            -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
-            1.0, 1.0, 1.0, 1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0,
-            1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0, -1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0,
-            1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0, 1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, -1.0,
-            -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0,
-        ];
+    pub unsafe fn try_from_config(config: SkyConfig) -> Result<Sky> {
+        let shader =
+            Shader::try_from_file("resources/shaders/skybox.vs", "resources/shaders/skybox.fs")?;
+        let faces: Vec<&str> = config.faces.iter().map(|face| face.as_str()).collect();
+        let cubemap_texture = try_load_cubemap(&faces)?;
+
+        Ok(Self::with_resources(config, shader, cubemap_texture))
+    }
 
-        // skybox VAO
-        let (mut skybox_vao, mut skybox_vbo) = (0, 0);
-        gl::GenVertexArrays(1, &mut skybox_vao);
-        gl::GenBuffers(1, &mut skybox_vbo);
-        gl::BindVertexArray(skybox_vao);
-        gl::BindBuffer(gl::ARRAY_BUFFER, skybox_vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (skybox_vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-            &skybox_vertices[0] as *const f32 as *const c_void,
-            gl::STATIC_DRAW,
-        );
-        gl::EnableVertexAttribArray(0);
-        let stride = 3 * mem::size_of::<GLfloat>() as GLsizei;
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+    unsafe fn with_resources(config: SkyConfig, shader: Shader, cubemap_texture: u32) -> Sky {
+        // only positions are used by the skybox shader
+        let cube = primitives::cube(2.0);
+        let cube = Mesh::new(cube.vertices, cube.indices, Vec::new());
 
-        let faces = [
-            "resources/objects/skybox/right.jpg",
-            "resources/objects/skybox/left.jpg",
-            "resources/objects/skybox/top.jpg",
-            "resources/objects/skybox/bottom.jpg",
-            "resources/objects/skybox/back.jpg",
-            "resources/objects/skybox/front.jpg",
-        ];
-        let cubemap_texture = load_cubemap(&faces);
         shader.use_program();
-        shader.setInt(c_str!("skybox"), 0);
+        shader.set_uniform("skybox", 0);
 
         Sky {
+            config,
             shader,
-            texture_id: cubemap_texture,
-            vao: skybox_vao,
-            vbo: skybox_vbo,
+            texture: GlObject::from_raw(GlKind::Texture, cubemap_texture),
+            cube,
         }
     }
 
-    pub unsafe fn draw(&mut self, mut view: Matrix4<f32>, projection: Matrix4<f32>) {
+    /// Draws the sky using the `Camera` uniform block, has to be called after it was updated.
+    pub unsafe fn draw(&mut self) {
         gl::DepthFunc(gl::LEQUAL); // change depth function so depth test passes when values are equal to depth buffer's content
         self.shader.use_program();
-        // remove translation from the view matrix
-        view.w[0] = 0.0;
-        view.w[1] = 0.0;
-        view.w[2] = 0.0;
-        self.shader.set_mat4(c_str!("view"), &view);
-        self.shader.set_mat4(c_str!("projection"), &projection);
         // skybox cube
-        gl::BindVertexArray(self.vao);
         gl::ActiveTexture(gl::TEXTURE0);
-        gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.texture_id);
-        gl::DrawArrays(gl::TRIANGLES, 0, 36);
-        gl::BindVertexArray(0);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.texture.id());
+        self.cube.Draw(&self.shader);
         gl::DepthFunc(gl::LESS); // set depth function back to default
     }
 }
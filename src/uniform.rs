@@ -0,0 +1,237 @@
+//! Typed uniform values and the per program table of active uniforms.
+use cgmath::{Matrix, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+use gl::types::*;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+
+/// Value that can be written to a shader uniform.
+pub trait Uniform {
+    /// whether uniforms of the given GL type can be set from this value
+    fn accepts(kind: GLenum) -> bool;
+
+    /// writes the value to `location` of the currently bound program
+    unsafe fn apply(&self, location: i32);
+}
+
+impl<T: Uniform> Uniform for &T {
+    fn accepts(kind: GLenum) -> bool {
+        T::accepts(kind)
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        (*self).apply(location)
+    }
+}
+
+impl Uniform for f32 {
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform1f(location, *self);
+    }
+}
+
+impl Uniform for i32 {
+    fn accepts(kind: GLenum) -> bool {
+        matches!(
+            kind,
+            gl::INT
+                | gl::BOOL
+                | gl::SAMPLER_1D
+                | gl::SAMPLER_2D
+                | gl::SAMPLER_3D
+                | gl::SAMPLER_CUBE
+                | gl::SAMPLER_2D_SHADOW
+                | gl::SAMPLER_2D_ARRAY
+        )
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform1i(location, *self);
+    }
+}
+
+impl Uniform for u32 {
+    fn accepts(kind: GLenum) -> bool {
+        matches!(kind, gl::UNSIGNED_INT | gl::BOOL)
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform1ui(location, *self);
+    }
+}
+
+impl Uniform for bool {
+    fn accepts(kind: GLenum) -> bool {
+        matches!(kind, gl::BOOL | gl::INT)
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform1i(location, *self as i32);
+    }
+}
+
+impl Uniform for Vector2<f32> {
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_VEC2
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform2f(location, self.x, self.y);
+    }
+}
+
+impl Uniform for Vector3<f32> {
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_VEC3
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform3f(location, self.x, self.y, self.z);
+    }
+}
+
+impl Uniform for Vector4<f32> {
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_VEC4
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::Uniform4f(location, self.x, self.y, self.z, self.w);
+    }
+}
+
+impl Uniform for Matrix3<f32> {
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_MAT3
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::UniformMatrix3fv(location, 1, gl::FALSE, self.as_ptr());
+    }
+}
+
+impl Uniform for Matrix4<f32> {
+    fn accepts(kind: GLenum) -> bool {
+        kind == gl::FLOAT_MAT4
+    }
+
+    unsafe fn apply(&self, location: i32) {
+        gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr());
+    }
+}
+
+/// Active uniform reported by the driver after linking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UniformInfo {
+    pub location: i32,
+    /// GL type, e.g. `gl::FLOAT_VEC3`
+    pub kind: GLenum,
+    /// number of elements, bigger than 1 for arrays
+    pub size: i32,
+}
+
+/// Name to location table of a linked program.
+#[derive(Debug, Default)]
+pub(crate) struct UniformTable {
+    uniforms: HashMap<String, UniformInfo>,
+    /// names already reported as unknown or mismatched, each is logged once
+    reported: HashSet<String>,
+}
+
+impl UniformTable {
+    /// Enumerates the active uniforms of a linked program. Arrays are registered both
+    /// under their base name and under every `name[i]`, uniforms living in uniform
+    /// blocks have no location and are skipped.
+    pub unsafe fn introspect(program: u32) -> Self {
+        let mut table = UniformTable::default();
+        let mut count = 0;
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        let mut max_length = 0;
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+        let mut buffer = vec![0u8; max_length.max(1) as usize];
+
+        for i in 0..count.max(0) as u32 {
+            let (mut length, mut size, mut kind) = (0, 0, 0);
+            gl::GetActiveUniform(
+                program,
+                i,
+                buffer.len() as GLsizei,
+                &mut length,
+                &mut size,
+                &mut kind,
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+            let name = String::from_utf8_lossy(&buffer[..length.max(0) as usize]).into_owned();
+            let base = name.strip_suffix("[0]").unwrap_or(&name).to_string();
+
+            for element in 0..size.max(1) {
+                let element_name = if size > 1 || base != name {
+                    format!("{}[{}]", base, element)
+                } else {
+                    base.clone()
+                };
+                let location = location_of(program, &element_name);
+                if location < 0 {
+                    continue;
+                }
+                let info = UniformInfo {
+                    location,
+                    kind,
+                    size: size - element,
+                };
+                if element == 0 {
+                    table.uniforms.insert(base.clone(), info);
+                }
+                table.uniforms.insert(element_name, info);
+            }
+        }
+
+        table
+    }
+
+    pub fn get(&self, name: &str) -> Option<UniformInfo> {
+        self.uniforms.get(name).copied()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.uniforms.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Location for a value of type `T`, unknown names and type mismatches are logged once.
+    pub fn location<T: Uniform>(&mut self, program: u32, name: &str) -> Option<i32> {
+        match self.uniforms.get(name) {
+            Some(info) if T::accepts(info.kind) => Some(info.location),
+            Some(info) => {
+                if self.reported.insert(name.to_string()) {
+                    warn!(
+                        "Uniform {} of shader {} has type 0x{:x}, cannot set it from {}",
+                        name,
+                        program,
+                        info.kind,
+                        std::any::type_name::<T>()
+                    );
+                }
+                None
+            }
+            None => {
+                if self.reported.insert(name.to_string()) {
+                    warn!("Shader {} has no active uniform {}", program, name);
+                }
+                None
+            }
+        }
+    }
+}
+
+unsafe fn location_of(program: u32, name: &str) -> i32 {
+    match CString::new(name) {
+        Ok(name) => gl::GetUniformLocation(program, name.as_ptr()),
+        Err(_) => -1,
+    }
+}
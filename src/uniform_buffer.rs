@@ -0,0 +1,214 @@
+//! std140 uniform blocks shared by all shader programs.
+//!
+//! Programs declaring a `Camera` or `Lights` block get it bound to a fixed binding
+//! point right after linking, so the data uploaded once per frame through
+//! `SharedUniforms` is visible in every shader. The engine owns the buffers and
+//! lets the client fill them in `Client::update_uniforms`. GLSL declarations live in
+//! `resources/shaders/common/camera.glsl` and `common/lighting.glsl`.
+use crate::gpu::{GlKind, GlObject};
+use crate::light::{DirectionalLight, LightingSystem, PointLight};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Vector2, Vector3, Vector4};
+use std::os::raw::c_void;
+
+pub const CAMERA_BLOCK: &str = "Camera";
+pub const CAMERA_BINDING: u32 = 0;
+pub const LIGHTS_BLOCK: &str = "Lights";
+pub const LIGHTS_BINDING: u32 = 1;
+
+/// Uniform block names and the binding points they are attached to.
+pub const BLOCK_BINDINGS: [(&str, u32); 2] = [
+    (CAMERA_BLOCK, CAMERA_BINDING),
+    (LIGHTS_BLOCK, LIGHTS_BINDING),
+];
+
+/// Writes values following the std140 layout rules. Values have to be written
+/// in the order they are declared in the GLSL block.
+#[derive(Debug, Clone, Default)]
+pub struct Std140 {
+    bytes: Vec<u8>,
+}
+
+impl Std140 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn align(&mut self, alignment: usize) {
+        let padding = (alignment - self.bytes.len() % alignment) % alignment;
+        self.bytes.resize(self.bytes.len() + padding, 0);
+    }
+
+    fn push(&mut self, alignment: usize, values: &[f32]) -> &mut Self {
+        self.align(alignment);
+        for value in values {
+            self.bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        self
+    }
+
+    pub fn float(&mut self, value: f32) -> &mut Self {
+        self.push(4, &[value])
+    }
+
+    pub fn int(&mut self, value: i32) -> &mut Self {
+        self.align(4);
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    pub fn vec2(&mut self, value: Vector2<f32>) -> &mut Self {
+        self.push(8, &[value.x, value.y])
+    }
+
+    pub fn vec3(&mut self, value: Vector3<f32>) -> &mut Self {
+        self.push(16, &[value.x, value.y, value.z])
+    }
+
+    pub fn vec4(&mut self, value: Vector4<f32>) -> &mut Self {
+        self.push(16, &[value.x, value.y, value.z, value.w])
+    }
+
+    pub fn mat4(&mut self, value: &Matrix4<f32>) -> &mut Self {
+        let columns: &[f32; 16] = value.as_ref();
+        self.push(16, columns)
+    }
+
+    /// Structs and array elements start and end on a 16 byte boundary.
+    pub fn begin_struct(&mut self) -> &mut Self {
+        self.align(16);
+        self
+    }
+
+    pub fn end_struct(&mut self) -> &mut Self {
+        self.align(16);
+        self
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// GL buffer attached to a uniform block binding point.
+#[derive(Debug)]
+pub struct UniformBuffer {
+    pub binding: u32,
+    buffer: GlObject,
+    size: usize,
+}
+
+impl UniformBuffer {
+    pub unsafe fn new(binding: u32) -> Self {
+        UniformBuffer {
+            binding,
+            buffer: GlObject::generate(GlKind::Buffer),
+            size: 0,
+        }
+    }
+
+    /// Uploads the data and binds the buffer to its binding point.
+    pub unsafe fn update(&mut self, data: &Std140) {
+        gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer.id());
+        if data.len() > self.size {
+            self.size = data.len();
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                self.size as isize,
+                data.as_bytes().as_ptr() as *const c_void,
+                gl::DYNAMIC_DRAW,
+            );
+        } else {
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                data.len() as isize,
+                data.as_bytes().as_ptr() as *const c_void,
+            );
+        }
+        gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, self.binding, self.buffer.id());
+    }
+}
+
+/// Attaches the shared blocks declared by `program` to their binding points.
+pub(crate) unsafe fn bind_blocks(program: u32) {
+    for (name, binding) in BLOCK_BINDINGS.iter() {
+        let name = std::ffi::CString::new(*name).unwrap();
+        let index = gl::GetUniformBlockIndex(program, name.as_ptr());
+        if index != gl::INVALID_INDEX {
+            gl::UniformBlockBinding(program, index, *binding);
+        }
+    }
+}
+
+/// `Camera` block contents.
+pub fn camera_block(
+    projection: &Matrix4<f32>,
+    view: &Matrix4<f32>,
+    view_pos: Vector3<f32>,
+) -> Std140 {
+    let mut block = Std140::new();
+    block.mat4(projection).mat4(view).vec3(view_pos);
+    block
+}
+
+/// `Lights` block contents, the number of point lights has to match `NR_POINT_LIGHTS`.
+pub fn lights_block(directional: &DirectionalLight, points: &[PointLight]) -> Std140 {
+    let mut block = Std140::new();
+    directional.write_std140(&mut block);
+    for light in points {
+        light.write_std140(&mut block);
+    }
+    block
+}
+
+/// Camera and light buffers updated once per frame and shared by every program.
+/// Until updated they hold identity camera matrices and the default lights.
+#[derive(Debug)]
+pub struct SharedUniforms {
+    camera: UniformBuffer,
+    lights: UniformBuffer,
+}
+
+impl Default for SharedUniforms {
+    fn default() -> Self {
+        unsafe {
+            let mut uniforms = SharedUniforms {
+                camera: UniformBuffer::new(CAMERA_BINDING),
+                lights: UniformBuffer::new(LIGHTS_BINDING),
+            };
+            let identity = Matrix4::identity();
+            uniforms.update_camera(&identity, &identity, Vector3::zero());
+            uniforms.update_lights(
+                &DirectionalLight::default(),
+                &LightingSystem::default_point_lights(),
+            );
+            uniforms
+        }
+    }
+}
+
+impl SharedUniforms {
+    pub unsafe fn update_camera(
+        &mut self,
+        projection: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+        view_pos: Vector3<f32>,
+    ) {
+        self.camera
+            .update(&camera_block(projection, view, view_pos));
+    }
+
+    pub unsafe fn update_lights(&mut self, directional: &DirectionalLight, points: &[PointLight]) {
+        self.lights.update(&lights_block(directional, points));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::{DopplerError, Result};
 use gl;
 use image2::image::Image;
 use image2::{io, ImagePtr, Rgb, Rgba};
@@ -9,44 +10,137 @@ pub fn path_exists(path: &std::path::Path) -> bool {
     meta.is_ok()
 }
 
-pub unsafe fn load_texture(path: &str, file_format: &str) -> u32 {
-    info!("Loading texture: {}", path);
-    let mut id = 0;
+/// Modification time of the file, `None` when it cannot be read.
+pub fn modified(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
 
-    gl::GenTextures(1, &mut id);
-    let content = io::read_u8(path);
-    if content.is_err() {
-        error!("Error: {:?}", content.err());
-        panic!();
+/// Current UTC time formatted as `YYYY-MM-DD_HH-MM-SS.mmm`, safe to use in file names.
+pub fn timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs() as i64;
+    let (days, day_secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // civil date from days since epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}.{:03}",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60,
+        now.subsec_millis()
+    )
+}
+
+/// Decoded image ready to be uploaded to the GPU.
+#[derive(Debug, Clone)]
+pub struct ImageData {
+    pub width: i32,
+    pub height: i32,
+    /// `gl::RGB` or `gl::RGBA`
+    pub format: u32,
+    pub data: Vec<u8>,
+}
+
+pub fn decode_image(path: &str, file_format: &str) -> Result<ImageData> {
+    let error = |e| DopplerError::Image {
+        path: path.into(),
+        message: format!("{:?}", e),
+    };
+    if !path_exists(std::path::Path::new(path)) {
+        return Err(DopplerError::io(
+            path,
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        ));
     }
-    let (data, dim, format) = match file_format {
+    let (data, (width, height), format) = match file_format {
         "png" => {
-            let img: ImagePtr<u8, Rgba> = content.unwrap();
-            let img_data = img.data().to_vec();
+            let img: ImagePtr<u8, Rgba> = io::read_u8(path).map_err(error)?;
             let (x, y, _) = img.shape();
 
-            (img_data, (x as i32, y as i32), gl::RGBA)
+            (img.data().to_vec(), (x as i32, y as i32), gl::RGBA)
         }
         _ => {
-            let img: ImagePtr<u8, Rgb> = io::read_u8(path).unwrap();
-            let img_data = img.data().to_vec();
+            let img: ImagePtr<u8, Rgb> = io::read_u8(path).map_err(error)?;
             let (x, y, _) = img.shape();
 
-            (img_data, (x as i32, y as i32), gl::RGB)
+            (img.data().to_vec(), (x as i32, y as i32), gl::RGB)
         }
     };
+    if data.is_empty() {
+        return Err(DopplerError::Image {
+            path: path.into(),
+            message: "image is empty".into(),
+        });
+    }
+
+    Ok(ImageData {
+        width,
+        height,
+        format,
+        data,
+    })
+}
 
+/// Magenta and black checkerboard used in place of assets that failed to load.
+pub fn checkerboard(size: i32, cell: i32) -> ImageData {
+    let mut data = Vec::with_capacity((size * size * 3) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            if (x / cell + y / cell) % 2 == 0 {
+                data.extend_from_slice(&[255, 0, 255]);
+            } else {
+                data.extend_from_slice(&[0, 0, 0]);
+            }
+        }
+    }
+
+    ImageData {
+        width: size,
+        height: size,
+        format: gl::RGB,
+        data,
+    }
+}
+
+pub unsafe fn upload_texture(image: &ImageData) -> u32 {
+    let mut id = 0;
+    gl::GenTextures(1, &mut id);
+    upload_texture_to(id, image);
+
+    id
+}
+
+/// Replaces the image of an existing texture, e.g. when it is hot reloaded.
+pub unsafe fn upload_texture_to(id: u32, image: &ImageData) {
     gl::BindTexture(gl::TEXTURE_2D, id);
+    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
     gl::TexImage2D(
         gl::TEXTURE_2D,
         0,
-        format as i32,
-        dim.0,
-        dim.1,
+        image.format as i32,
+        image.width,
+        image.height,
         0,
-        format,
+        image.format,
         gl::UNSIGNED_BYTE,
-        &data[0] as *const u8 as *const c_void,
+        &image.data[0] as *const u8 as *const c_void,
     );
     gl::GenerateMipmap(gl::TEXTURE_2D);
 
@@ -58,8 +152,28 @@ pub unsafe fn load_texture(path: &str, file_format: &str) -> u32 {
         gl::LINEAR_MIPMAP_LINEAR as i32,
     );
     gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+}
 
-    id
+pub unsafe fn placeholder_texture() -> u32 {
+    upload_texture(&checkerboard(64, 8))
+}
+
+pub unsafe fn try_load_texture(path: &str, file_format: &str) -> Result<u32> {
+    info!("Loading texture: {}", path);
+    let image = decode_image(path, file_format)?;
+
+    Ok(upload_texture(&image))
+}
+
+/// Loads texture, falling back to a placeholder checkerboard when it cannot be read.
+pub unsafe fn load_texture(path: &str, file_format: &str) -> u32 {
+    match try_load_texture(path, file_format) {
+        Ok(id) => id,
+        Err(e) => {
+            error!("{}, using placeholder texture", e);
+            placeholder_texture()
+        }
+    }
 }
 
 pub unsafe fn load_texture_from_dir(filename: &str, directory: &str) -> u32 {
@@ -69,10 +183,15 @@ pub unsafe fn load_texture_from_dir(filename: &str, directory: &str) -> u32 {
 }
 
 pub unsafe fn load_texture_from_fullpath(fullpath: &str) -> u32 {
+    load_texture(fullpath, image_format(fullpath))
+}
+
+/// Format passed to `decode_image`, everything after the first dot of the path.
+pub fn image_format(fullpath: &str) -> &str {
     let dot = fullpath.find('.').unwrap_or_default() + 1usize;
     let (_, format) = fullpath.split_at(dot);
 
-    load_texture(fullpath, format)
+    format
 }
 
 /// loads a cubemap texture from 6 individual texture faces
@@ -84,34 +203,96 @@ pub unsafe fn load_texture_from_fullpath(fullpath: &str) -> u32 {
 /// +Z (front)
 /// -Z (back)
 /// -------------------------------------------------------
+pub unsafe fn try_load_cubemap(faces: &[&str]) -> Result<u32> {
+    let mut images = Vec::with_capacity(faces.len());
+    for face in faces {
+        info!("Loading {}", face);
+        images.push(decode_image(face, "")?);
+    }
+
+    Ok(upload_cubemap(&images))
+}
+
+/// Same as `try_load_cubemap`, but faces that cannot be loaded are replaced with
+/// a placeholder checkerboard.
 pub unsafe fn load_cubemap(faces: &[&str]) -> u32 {
+    let images = faces
+        .iter()
+        .map(|face| {
+            info!("Loading {}", face);
+            decode_image(face, "")
+        })
+        .collect();
+
+    upload_cubemap(&cubemap_faces(images))
+}
+
+/// Replaces faces that failed to load with checkerboards. A cubemap is only complete when
+/// all faces are square with the same size and format, so the checkerboards copy those of
+/// the loaded faces. When the loaded faces don't agree, all faces become checkerboards.
+pub fn cubemap_faces(faces: Vec<Result<ImageData>>) -> Vec<ImageData> {
+    let mut loaded = faces
+        .iter()
+        .filter_map(|face| face.as_ref().ok())
+        .map(|face| (face.width, face.height, face.format));
+    let first = loaded.next();
+    let consistent = match first {
+        Some(first) => first.0 == first.1 && loaded.all(|face| face == first),
+        None => false,
+    };
+    let (size, format) = match first {
+        Some((width, _, format)) if consistent => (width, format),
+        Some(_) => {
+            error!("Cubemap faces differ in size or format, using placeholder faces");
+            (64, gl::RGB)
+        }
+        None => (64, gl::RGB),
+    };
+
+    faces
+        .into_iter()
+        .map(|face| match face {
+            Ok(image) if consistent => image,
+            Ok(_) => checkerboard_face(size, format),
+            Err(e) => {
+                error!("{}, using placeholder face", e);
+                checkerboard_face(size, format)
+            }
+        })
+        .collect()
+}
+
+/// `checkerboard` with cells an eighth of the size, in `gl::RGB` or `gl::RGBA`.
+fn checkerboard_face(size: i32, format: u32) -> ImageData {
+    let mut image = checkerboard(size, (size / 8).max(1));
+    if format == gl::RGBA {
+        image.data = image
+            .data
+            .chunks_exact(3)
+            .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 255])
+            .collect();
+        image.format = gl::RGBA;
+    }
+    image
+}
+
+unsafe fn upload_cubemap(faces: &[ImageData]) -> u32 {
     let mut texture_id = 0;
     gl::GenTextures(1, &mut texture_id);
     gl::BindTexture(gl::TEXTURE_CUBE_MAP, texture_id);
+    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
 
     for (i, face) in faces.iter().enumerate() {
-        if !path_exists(std::path::Path::new(&face)) {
-            error!("There is no file {}", face);
-        } else {
-            info!("Loading {}", face);
-        }
-        let (data, dim) = {
-            let img: ImagePtr<u8, Rgb> = io::read_u8(face).unwrap();
-            let img_data = img.data().to_vec();
-            let (x, y, _) = img.shape();
-
-            (img_data, (x as i32, y as i32))
-        };
         gl::TexImage2D(
             gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
             0,
-            gl::RGB as i32,
-            dim.0,
-            dim.1,
+            face.format as i32,
+            face.width,
+            face.height,
             0,
-            gl::RGB,
+            face.format,
             gl::UNSIGNED_BYTE,
-            &data[0] as *const u8 as *const c_void,
+            &face.data[0] as *const u8 as *const c_void,
         );
     }
 
@@ -0,0 +1,130 @@
+use doppler::assets_cache::{AssetsCache, Handle};
+use doppler::error::DopplerError;
+use doppler::model::Model;
+use doppler::utils;
+
+#[test]
+fn missing_image_is_an_io_error() {
+    match utils::decode_image("resources/does_not_exist.png", "png") {
+        Err(DopplerError::Io { path, source }) => {
+            assert_eq!(path, "resources/does_not_exist.png");
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        other => panic!("expected io error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn checkerboard_alternates_magenta_and_black() {
+    let image = utils::checkerboard(4, 2);
+
+    assert_eq!((image.width, image.height), (4, 4));
+    assert_eq!(image.data.len(), 4 * 4 * 3);
+    assert_eq!(&image.data[0..3], &[255, 0, 255]);
+    assert_eq!(&image.data[6..9], &[0, 0, 0]);
+    // second row of cells starts with black
+    assert_eq!(&image.data[2 * 4 * 3..2 * 4 * 3 + 3], &[0, 0, 0]);
+}
+
+#[test]
+fn missing_cubemap_face_matches_loaded_faces() {
+    let face = || {
+        let mut image = utils::checkerboard(128, 16);
+        image.data = image
+            .data
+            .chunks_exact(3)
+            .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 255])
+            .collect();
+        image.format = gl::RGBA;
+        Ok(image)
+    };
+    let mut faces: Vec<_> = (0..6).map(|_| face()).collect();
+    faces[2] = utils::decode_image("resources/does_not_exist.png", "png");
+
+    let faces = utils::cubemap_faces(faces);
+
+    assert_eq!(faces.len(), 6);
+    for face in &faces {
+        assert_eq!((face.width, face.height, face.format), (128, 128, gl::RGBA));
+        assert_eq!(face.data.len(), 128 * 128 * 4);
+    }
+}
+
+#[test]
+fn mismatched_cubemap_faces_are_all_replaced() {
+    let mut faces: Vec<_> = (0..6).map(|_| Ok(utils::checkerboard(32, 4))).collect();
+    faces[0] = Ok(utils::checkerboard(16, 4));
+
+    let faces = utils::cubemap_faces(faces);
+
+    for face in &faces {
+        assert_eq!((face.width, face.height, face.format), (64, 64, gl::RGB));
+    }
+}
+
+#[test]
+fn models_are_cached_per_texture_override() {
+    let path = "resources/built_in.obj";
+    let mut cache = AssetsCache::default();
+    cache.add_model(path, Model::default());
+
+    assert!(cache.has_model(path));
+    assert!(cache.has_model_ext(path, None));
+    assert!(!cache.has_model_ext(path, Some("red.png")));
+    assert_eq!(
+        cache
+            .legacy_models()
+            .values()
+            .filter(|(p, _)| p == path)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn handles_share_assets_until_modified() {
+    let mut first = Handle::new(vec![1, 2, 3]);
+    let second = first.clone();
+    assert!(first.ptr_eq(&second));
+    assert_eq!(second.ref_count(), 2);
+
+    first.make_mut().push(4);
+    assert!(!first.ptr_eq(&second));
+    assert_eq!(*second.borrow(), vec![1, 2, 3]);
+    assert_eq!(first.borrow().len(), 4);
+}
+
+#[test]
+fn changes_through_borrow_mut_are_shared() {
+    let first = Handle::new(vec![1, 2, 3]);
+    let second = first.clone();
+
+    first.borrow_mut().push(4);
+    assert!(first.ptr_eq(&second));
+    assert_eq!(*second.borrow(), vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn unloading_an_empty_cache_frees_nothing() {
+    let mut cache = AssetsCache::default();
+
+    assert_eq!(cache.unload_unused(), 0);
+    assert_eq!((cache.loaded_models(), cache.loaded_textures()), (0, 0));
+}
+
+#[test]
+fn replaced_assets_are_seen_by_every_handle() {
+    let first = Handle::new(vec![1, 2, 3]);
+    let second = first.clone();
+
+    assert_eq!(first.replace(vec![4]), vec![1, 2, 3]);
+    assert!(first.ptr_eq(&second));
+    assert_eq!(*second.borrow(), vec![4]);
+}
+
+#[test]
+fn polling_an_empty_cache_reloads_nothing() {
+    let mut cache = AssetsCache::default();
+
+    assert_eq!(cache.poll_changes(), 0);
+}
@@ -0,0 +1,82 @@
+use cgmath::{vec3, Deg, Matrix4};
+use doppler::assets_cache::Handle;
+use doppler::bounds::{Aabb, BoundingSphere, Bounds};
+use doppler::components::{ModelComponent, Transform};
+use doppler::mesh::primitives;
+use doppler::model::Model;
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+#[test]
+fn primitive_bounds() {
+    let cube = primitives::cube(1.0);
+    let positions: Vec<_> = cube.vertices.iter().map(|v| v.position).collect();
+    let bounds = Bounds::from_points(&positions);
+
+    assert_eq!(
+        bounds.aabb,
+        Aabb::new(vec3(-0.5, -0.5, -0.5), vec3(0.5, 0.5, 0.5))
+    );
+    assert_eq!(bounds.sphere.center, vec3(0.0, 0.0, 0.0));
+    assert!(close(bounds.sphere.radius, 0.75f32.sqrt()));
+    assert!(Bounds::from_points(&[]).is_empty());
+}
+
+#[test]
+fn transformed_box_contains_rotated_corners() {
+    let aabb = Aabb::new(vec3(0.0, 0.0, 0.0), vec3(2.0, 1.0, 1.0));
+    let matrix = Matrix4::from_angle_z(Deg(90.0));
+    let rotated = aabb.transform(&matrix);
+
+    assert!(close(rotated.min.x, -1.0) && close(rotated.max.x, 0.0));
+    assert!(close(rotated.min.y, 0.0) && close(rotated.max.y, 2.0));
+    assert!(Aabb::empty().transform(&matrix).is_empty());
+}
+
+#[test]
+fn sphere_union_contains_both_spheres() {
+    let a = BoundingSphere::new(vec3(0.0, 0.0, 0.0), 1.0);
+    let b = BoundingSphere::new(vec3(4.0, 0.0, 0.0), 1.0);
+    let union = a.union(&b);
+
+    assert_eq!(union.center, vec3(2.0, 0.0, 0.0));
+    assert_eq!(union.radius, 3.0);
+    assert_eq!(a.union(&BoundingSphere::new(vec3(0.5, 0.0, 0.0), 0.25)), a);
+    assert_eq!(BoundingSphere::empty().union(&b), b);
+    assert!(!a.intersects(&b) && union.intersects(&b));
+}
+
+#[test]
+fn empty_sphere_contains_nothing() {
+    let empty = BoundingSphere::empty();
+
+    assert!(!empty.contains(vec3(0.0, 0.0, 0.0)));
+    assert!(!empty.contains(vec3(0.5, 0.0, 0.0)));
+    assert!(BoundingSphere::new(vec3(0.0, 0.0, 0.0), 0.0).contains(vec3(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn model_component_bounds_follow_the_transform() {
+    let mut model = Model::default();
+    model.bounds = Bounds::from_points(&[vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0)]);
+    let component = ModelComponent {
+        model: Handle::new(model),
+        transform: Transform {
+            position: vec3(10.0, 0.0, 0.0),
+            scale: 2.0,
+            ..Transform::default()
+        },
+        ..ModelComponent::default()
+    };
+    let bounds = component.bounds();
+
+    assert_eq!(
+        bounds.aabb,
+        Aabb::new(vec3(8.0, -2.0, -2.0), vec3(12.0, 2.0, 2.0))
+    );
+    assert_eq!(bounds.sphere.center, vec3(10.0, 0.0, 0.0));
+    assert!(close(bounds.sphere.radius, 2.0 * 3f32.sqrt()));
+    assert!(bounds.aabb.contains(vec3(11.0, 1.5, 0.0)));
+}
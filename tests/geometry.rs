@@ -0,0 +1,140 @@
+use cgmath::{vec2, vec3, InnerSpace, Vector2, Vector3};
+use doppler::geometry::{generate_normals, generate_tangents};
+use doppler::mesh::Vertex;
+
+/// Unit quad in the XY plane facing +Z, `u` runs along +X unless mirrored.
+fn quad(mirrored: bool) -> (Vec<Vertex>, Vec<u32>) {
+    let corners: [(Vector3<f32>, Vector2<f32>); 4] = [
+        (vec3(0.0, 0.0, 0.0), vec2(0.0, 0.0)),
+        (vec3(1.0, 0.0, 0.0), vec2(1.0, 0.0)),
+        (vec3(1.0, 1.0, 0.0), vec2(1.0, 1.0)),
+        (vec3(0.0, 1.0, 0.0), vec2(0.0, 1.0)),
+    ];
+    let vertices = corners
+        .iter()
+        .map(|&(position, uv)| Vertex {
+            position,
+            text_coords: if mirrored { vec2(1.0 - uv.x, uv.y) } else { uv },
+            ..Vertex::default()
+        })
+        .collect();
+
+    (vertices, vec![0, 1, 2, 0, 2, 3])
+}
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn normals_are_generated_from_winding() {
+    let (mut vertices, indices) = quad(false);
+    generate_normals(&mut vertices, &indices);
+
+    for vertex in &vertices {
+        assert_close(vertex.normal, vec3(0.0, 0.0, 1.0));
+    }
+}
+
+#[test]
+fn tangents_follow_texture_coordinates() {
+    let (mut vertices, mut indices) = quad(false);
+    generate_normals(&mut vertices, &indices);
+    generate_tangents(&mut vertices, &mut indices);
+
+    for vertex in &vertices {
+        assert_close(vertex.tangent, vec3(1.0, 0.0, 0.0));
+        assert_close(vertex.bitangent, vec3(0.0, 1.0, 0.0));
+    }
+}
+
+#[test]
+fn mirrored_texture_coordinates_flip_the_tangent() {
+    let (mut vertices, mut indices) = quad(true);
+    generate_normals(&mut vertices, &indices);
+    generate_tangents(&mut vertices, &mut indices);
+
+    for vertex in &vertices {
+        assert_close(vertex.tangent, vec3(-1.0, 0.0, 0.0));
+        assert_close(vertex.bitangent, vec3(0.0, 1.0, 0.0));
+    }
+}
+
+#[test]
+fn indices_out_of_range_are_skipped() {
+    let (mut vertices, _) = quad(false);
+    let mut indices = vec![0, 1, 2, 0, 2, 7];
+    generate_normals(&mut vertices, &indices);
+    generate_tangents(&mut vertices, &mut indices);
+
+    assert_close(vertices[0].normal, vec3(0.0, 0.0, 1.0));
+    // not part of any valid triangle
+    assert_close(vertices[3].normal, vec3(0.0, 1.0, 0.0));
+}
+
+/// Curved 3x3 grid with texture coordinates mirrored at x = 0.
+fn mirrored_grid() -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    for row in 0..3 {
+        for column in 0..3 {
+            let (x, y) = (column as f32 - 1.0, row as f32);
+            vertices.push(Vertex {
+                position: vec3(x, y, 0.3 * x * x + 0.1 * x * y),
+                text_coords: vec2(0.5 * x.abs() + 0.05 * y, 0.5 * y + 0.1 * x),
+                ..Vertex::default()
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    for row in 0..2 {
+        for column in 0..2 {
+            let a = row * 3 + column;
+            indices.extend_from_slice(&[a, a + 1, a + 4, a, a + 4, a + 3]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+#[test]
+fn tangents_match_mikktspace_reference() {
+    // tangent and sign of each triangle corner computed by the reference implementation
+    let reference = [
+        [-0.954481, 0.191033, 0.229069, -1.0],
+        [-0.968016, 0.219363, -0.121757, -1.0],
+        [-0.974668, 0.201302, -0.097467, -1.0],
+        [-0.954481, 0.191033, 0.229069, -1.0],
+        [-0.974668, 0.201302, -0.097467, -1.0],
+        [-0.969790, 0.193349, 0.148742, -1.0],
+        [0.975257, -0.183071, 0.123932, 1.0],
+        [0.945587, -0.189117, 0.264764, 1.0],
+        [0.926641, -0.184684, 0.327456, 1.0],
+        [0.975257, -0.183071, 0.123932, 1.0],
+        [0.926641, -0.184684, 0.327456, 1.0],
+        [0.977028, -0.189393, 0.097703, 1.0],
+        [-0.969790, 0.193349, 0.148742, -1.0],
+        [-0.974668, 0.201302, -0.097467, -1.0],
+        [-0.978861, 0.191568, -0.071643, -1.0],
+        [-0.969790, 0.193349, 0.148742, -1.0],
+        [-0.978861, 0.191568, -0.071643, -1.0],
+        [-0.977577, 0.195516, 0.078206, -1.0],
+        [0.977028, -0.189393, 0.097703, 1.0],
+        [0.926641, -0.184684, 0.327456, 1.0],
+        [0.900292, -0.180482, 0.396107, 1.0],
+        [0.977028, -0.189393, 0.097703, 1.0],
+        [0.900292, -0.180482, 0.396107, 1.0],
+        [0.976464, -0.203351, 0.071876, 1.0],
+    ];
+    let (mut vertices, mut indices) = mirrored_grid();
+    generate_normals(&mut vertices, &indices);
+    generate_tangents(&mut vertices, &mut indices);
+
+    for (&index, expected) in indices.iter().zip(reference.iter()) {
+        let vertex = &vertices[index as usize];
+        assert_close(vertex.tangent, vec3(expected[0], expected[1], expected[2]));
+        let sign = vertex.normal.cross(vertex.tangent).dot(vertex.bitangent);
+        assert_eq!(sign.signum(), expected[3]);
+    }
+    // the three vertices on the mirror line are split
+    assert_eq!(vertices.len(), 12);
+}
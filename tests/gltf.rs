@@ -0,0 +1,127 @@
+use cgmath::{vec3, Matrix4};
+use doppler::model::Model;
+
+/// Triangle in a child node moved by its parent, with an embedded 1x1 red PNG.
+const TRIANGLE: &str = r#"{
+  "asset": { "version": "2.0" },
+  "scene": 0,
+  "scenes": [{ "nodes": [0] }],
+  "nodes": [
+    { "name": "root", "translation": [1.0, 0.0, 0.0], "children": [1] },
+    { "name": "triangle", "mesh": 0 }
+  ],
+  "meshes": [{
+    "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }]
+  }],
+  "materials": [{ "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } } }],
+  "textures": [{ "source": 0 }],
+  "images": [{
+    "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ/pLvAAAAAElFTkSuQmCC"
+  }],
+  "buffers": [{
+    "byteLength": 44,
+    "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="
+  }],
+  "bufferViews": [
+    { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+    { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+  ],
+  "accessors": [
+    { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+      "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+    { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+  ]
+}"#;
+
+fn write_triangle(name: &str) -> String {
+    write_gltf(name, TRIANGLE)
+}
+
+fn write_gltf(name: &str, json: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, json).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn gltf_keeps_node_tree_and_mesh_space_vertices() {
+    let path = write_triangle("doppler_triangle.gltf");
+    let data = Model::parse(&path, None, false).unwrap();
+
+    assert_eq!(data.roots, vec![0]);
+    assert_eq!(data.nodes.len(), 2);
+    assert_eq!(data.nodes[0].name, "root");
+    assert_eq!(data.nodes[0].children, vec![1]);
+    assert_eq!(data.nodes[1].meshes, vec![0]);
+
+    // vertices stay in mesh space, the parent translation is applied when drawing
+    let mesh = &data.meshes[0];
+    assert_eq!(mesh.indices, vec![0, 1, 2]);
+    assert_eq!(mesh.vertices[0].position, vec3(0.0, 0.0, 0.0));
+    assert_eq!(mesh.vertices[2].position, vec3(0.0, 1.0, 0.0));
+    assert_eq!(
+        data.mesh_instances(),
+        vec![(0, Matrix4::from_translation(vec3(1.0, 0.0, 0.0)))]
+    );
+}
+
+#[test]
+fn gltf_mesh_instanced_by_two_nodes_is_shared() {
+    let json = TRIANGLE.replace(
+        r#""scenes": [{ "nodes": [0] }],
+  "nodes": [
+    { "name": "root", "translation": [1.0, 0.0, 0.0], "children": [1] },
+    { "name": "triangle", "mesh": 0 }
+  ],"#,
+        r#""scenes": [{ "nodes": [0, 1] }],
+  "nodes": [
+    { "name": "left", "mesh": 0, "translation": [-2.0, 0.0, 0.0] },
+    { "name": "right", "mesh": 0, "translation": [2.0, 0.0, 0.0] }
+  ],"#,
+    );
+    assert_ne!(json, TRIANGLE);
+    let path = write_gltf("doppler_instanced.gltf", &json);
+    let data = Model::parse(&path, None, false).unwrap();
+
+    assert_eq!(data.meshes.len(), 1);
+    assert_eq!(data.nodes[0].meshes, vec![0]);
+    assert_eq!(data.nodes[1].meshes, vec![0]);
+    assert_eq!(data.meshes[0].vertices[1].position, vec3(1.0, 0.0, 0.0));
+    assert_eq!(
+        data.mesh_instances(),
+        vec![
+            (0, Matrix4::from_translation(vec3(-2.0, 0.0, 0.0))),
+            (0, Matrix4::from_translation(vec3(2.0, 0.0, 0.0))),
+        ]
+    );
+}
+
+#[test]
+fn gltf_embedded_images_are_decoded_with_the_model() {
+    let path = write_triangle("doppler_embedded.gltf");
+    let data = Model::parse(&path, None, false).unwrap();
+
+    let key = format!("{}#0", path);
+    assert_eq!(data.meshes[0].textures[0].file, key);
+    assert_eq!(data.meshes[0].textures[0].type_, "texture_diffuse");
+    let (texture, image) = &data.embedded[0];
+    assert_eq!(texture.file, key);
+    assert_eq!((image.width, image.height), (1, 1));
+    assert_eq!(image.data, vec![255, 0, 0]);
+    assert_eq!(data.external_textures().count(), 0);
+}
+
+#[test]
+fn gltf_image_uris_are_percent_decoded() {
+    let json = TRIANGLE.replace(
+        "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ/pLvAAAAAElFTkSuQmCC",
+        "textures/my%20tex%C3%A9.png",
+    );
+    assert_ne!(json, TRIANGLE);
+    let path = write_gltf("doppler_uri.gltf", &json);
+    let data = Model::parse(&path, None, false).unwrap();
+
+    let texture = &data.meshes[0].textures[0];
+    assert_eq!(texture.file, "textures/my tex\u{e9}.png");
+    assert!(data.embedded.is_empty());
+}
@@ -0,0 +1,52 @@
+use doppler::framebuffer::FrameCapture;
+use doppler::golden::{compare, GoldenError, GoldenTest};
+
+#[test]
+#[ignore = "needs a GL context, run with --ignored"]
+fn test_map_matches_golden_images() {
+    let test = GoldenTest::new("test_map", "tests/golden/map.yaml").with_frames(3);
+    if let Err(e) = test.run() {
+        panic!("{}", e);
+    }
+}
+
+#[test]
+fn missing_reference_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("doppler_golden_{}", std::process::id()));
+    let mut test = GoldenTest::new("missing", "unused.yaml");
+    test.reference_dir = dir.join("references");
+    test.output_dir = dir.join("output");
+    let capture = FrameCapture {
+        width: 1,
+        height: 1,
+        pixels: vec![0, 0, 0, 255],
+    };
+
+    match test.check("final", &capture) {
+        Err(GoldenError::MissingReference { reference, actual }) => {
+            assert!(!reference.exists());
+            assert!(actual.exists());
+        }
+        other => panic!("expected a missing reference, got {:?}", other),
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn compare_counts_pixels_over_tolerance() {
+    let expected = FrameCapture {
+        width: 2,
+        height: 1,
+        pixels: vec![10, 10, 10, 255, 200, 0, 0, 255],
+    };
+    let actual = FrameCapture {
+        width: 2,
+        height: 1,
+        pixels: vec![12, 9, 10, 255, 100, 0, 0, 255],
+    };
+    let comparison = compare(&expected, &actual, 2);
+
+    assert_eq!(comparison.mismatched, 1);
+    assert_eq!(comparison.max_difference, 100);
+    assert_eq!(&comparison.diff.pixels[4..], &[255, 0, 0, 255]);
+}
@@ -0,0 +1,37 @@
+use doppler::gpu::{live_objects, GlKind, GlObject, MeshHandle, TextureHandle};
+
+#[test]
+fn debug_builds_count_live_objects() {
+    let before = live_objects(GlKind::Renderbuffer);
+    let object = GlObject::from_raw(GlKind::Renderbuffer, 7);
+    if cfg!(debug_assertions) {
+        assert_eq!(live_objects(GlKind::Renderbuffer), before + 1);
+    }
+
+    assert_eq!(object.into_raw(), 7);
+    assert_eq!(live_objects(GlKind::Renderbuffer), before);
+}
+
+#[test]
+fn empty_objects_are_not_counted() {
+    let before = live_objects(GlKind::Framebuffer);
+    let object = GlObject::none(GlKind::Framebuffer);
+
+    assert_eq!(object.id(), 0);
+    assert_eq!(live_objects(GlKind::Framebuffer), before);
+}
+
+#[test]
+fn handles_share_the_object() {
+    // id 0 is never deleted, so this runs without a GL context
+    let texture = TextureHandle::from_raw(0);
+    let clone = texture.clone();
+    assert_eq!(texture.ref_count(), 2);
+    drop(clone);
+    assert_eq!(texture.ref_count(), 1);
+
+    let mesh = MeshHandle::default();
+    let clones = [mesh.clone(), mesh.clone()];
+    assert_eq!(mesh.ref_count(), 3);
+    assert_eq!(clones[0].vao(), 0);
+}
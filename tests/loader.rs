@@ -0,0 +1,54 @@
+use doppler::assets_cache::AssetsCache;
+use doppler::loader::LoadProgress;
+use doppler::model::Model;
+
+#[test]
+fn nothing_queued_is_done() {
+    let progress = LoadProgress::default();
+
+    assert!(progress.is_done());
+    assert_eq!(progress.fraction(), 1.0);
+}
+
+#[test]
+fn fraction_counts_uploaded_assets() {
+    let progress = LoadProgress { total: 4, done: 1 };
+
+    assert!(!progress.is_done());
+    assert_eq!(progress.fraction(), 0.25);
+    assert!(LoadProgress { total: 4, done: 4 }.is_done());
+}
+
+#[test]
+fn queued_model_is_counted_once() {
+    let mut cache = AssetsCache::default();
+    cache.load_model_async("resources/does_not_exist.obj", None);
+    cache.load_model_async("resources/does_not_exist.obj", None);
+
+    assert_eq!(cache.load_progress().total, 1);
+}
+
+#[test]
+fn model_cached_before_its_upload_is_kept() {
+    let path = "resources/queued_does_not_exist.obj";
+    let mut cache = AssetsCache::default();
+    cache.load_model_async(path, None);
+    // e.g. `get_model` loading it synchronously while the worker parses it
+    let cached = cache.add_model(path, Model::default());
+    let progress = cache.finish_loading();
+
+    assert_eq!(progress, LoadProgress { total: 1, done: 1 });
+    assert!(cache.get_model_by_path(path).unwrap().ptr_eq(&cached));
+    assert_eq!(cache.loaded_models(), 1);
+}
+
+#[test]
+fn texture_overrides_are_queued_separately() {
+    let path = "resources/does_not_exist.obj";
+    let mut cache = AssetsCache::default();
+    cache.load_model_async(path, None);
+    cache.load_model_async(path, Some("red.png"));
+    cache.load_model_async(path, Some("red.png"));
+
+    assert_eq!(cache.load_progress().total, 2);
+}
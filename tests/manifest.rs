@@ -0,0 +1,74 @@
+use doppler::manifest::AssetManifest;
+
+#[test]
+fn yaml_manifest_reports_every_bad_entry_with_line() {
+    let text = "\
+models:
+  - path: resources/objects/ground/ground.obj
+    tags: [terrain]
+  - path: resources/objects/missing.obj
+  - texture: no_path.png
+shaders:
+  - name: lamp
+    vertex: resources/shaders/lamp.vs
+    fragment: resources/shaders/lamp.fs
+  - name: lamp
+    vertex: resources/shaders/lamp.vs
+    fragment: resources/shaders/lamp.fs
+";
+    let (manifest, issues) = AssetManifest::parse_yaml(text);
+
+    assert_eq!(manifest.models.len(), 1);
+    assert_eq!(manifest.models[0].tags, vec!["terrain".to_string()]);
+    assert_eq!(manifest.shaders.len(), 1);
+    let lines: Vec<Option<usize>> = issues.iter().map(|issue| issue.line).collect();
+    assert_eq!(lines, vec![Some(4), Some(5), Some(10)]);
+}
+
+#[test]
+fn json_manifest_reports_every_bad_entry_with_line() {
+    let text = r#"{
+  "models": [
+    { "path": "resources/objects/ground/ground.obj" },
+    { "path": "resources/objects/ground/ground.obj" }
+  ],
+  "cubemaps": [
+    {
+      "name": "sky",
+      "faces": ["resources/defaults/plane.obj"]
+    }
+  ],
+  "meshes": []
+}"#;
+    let (manifest, issues) = AssetManifest::parse_json(text);
+
+    assert_eq!(manifest.models.len(), 1);
+    assert!(manifest.cubemaps.is_empty());
+    let lines: Vec<Option<usize>> = issues.iter().map(|issue| issue.line).collect();
+    assert_eq!(lines, vec![None, Some(4), Some(7)]);
+}
+
+#[test]
+fn example_manifest_lists_the_shipped_objects_list_models() {
+    let (manifest, issues) = AssetManifest::load("resources/assets.yaml");
+    assert!(issues.is_empty(), "{:?}", issues);
+    let listed: Vec<String> = manifest
+        .models
+        .into_iter()
+        .map(|model| model.path)
+        .collect();
+
+    let objects = std::fs::read_to_string("resources/test_objects.txt").unwrap();
+    // the list also names models and textures that are not in the repository
+    let shipped = objects
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|model| std::path::Path::new(model).exists());
+    for model in shipped {
+        assert!(
+            listed.iter().any(|path| path == model),
+            "{} is not in the manifest",
+            model
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use doppler::assets_cache::AssetsCache;
+use doppler::map::MapSave;
+
+#[test]
+fn map_save_round_trips_through_file() {
+    let save = MapSave::read("resources/test_map.yaml").unwrap();
+    let path = std::env::temp_dir().join("doppler_round_trip.yaml");
+    let path = path.to_str().unwrap();
+
+    save.write(path).unwrap();
+    let loaded = MapSave::read(path).unwrap();
+
+    assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+    assert_eq!(
+        serde_yaml::to_string(&save).unwrap(),
+        serde_yaml::to_string(&loaded).unwrap()
+    );
+    assert_eq!(loaded.objects.len(), save.objects.len());
+    std::fs::remove_file(path).unwrap();
+}
+
+const CAMERA: &str = "
+camera:
+  position: {x: 0.0, y: 8.0, z: 13.0}
+  front: {x: 0.0, y: -0.4, z: -1.0}
+  up: {x: 0.0, y: 1.0, z: -0.4}
+  right: {x: 1.0, y: 0.0, z: 0.0}
+  worldup: {x: 0.0, y: 1.0, z: 0.0}
+  yaw: -90.0
+  pitch: -20.0
+  movement_speed: 0.5
+  mouse_sensivity: 0.1
+  zoom: 45.0
+";
+
+#[test]
+fn legacy_map_objects_keep_their_hash() {
+    // `DefaultHasher` output changes between Rust releases, so the hash is not hardcoded
+    let hash = AssetsCache::legacy_path_hash("resources/objects/ground/ground.obj");
+    let legacy = format!(
+        "
+objects:
+  - model_hash: {}
+    transform:
+      position: {{x: 0.0, y: 0.0, z: 0.0}}
+      rotation: {{x: 0.0, y: 0.0, z: 0.0}}
+      scale: 1.0
+",
+        hash
+    );
+    let save: MapSave = serde_yaml::from_str(&format!("{}{}", legacy, CAMERA)).unwrap();
+
+    assert_eq!(save.objects[0].model, None);
+    assert_eq!(save.objects[0].model_hash, Some(hash));
+}
+
+#[test]
+fn legacy_hashes_resolve_in_an_empty_cache() {
+    let object = |hash: u64| {
+        format!(
+            "
+  - model_hash: {}
+    transform:
+      position: {{x: 0.0, y: 0.0, z: 0.0}}
+      rotation: {{x: 0.0, y: 0.0, z: 0.0}}
+      scale: 1.0",
+            hash
+        )
+    };
+    let legacy = format!(
+        "objects:{}{}{}{}{}",
+        object(AssetsCache::legacy_path_hash(
+            "resources/objects/ground/ground.obj"
+        )),
+        object(AssetsCache::legacy_path_hash(
+            "resources/objects/grass/grass.obj"
+        )),
+        object(AssetsCache::legacy_path_hash(
+            "resources/objects/gaz_tank/gaz_tank.obj"
+        )),
+        object(1),
+        CAMERA
+    );
+    let save: MapSave = serde_yaml::from_str(&legacy).unwrap();
+    let cache = AssetsCache::default();
+
+    assert_eq!(
+        save.resolve_models(&cache),
+        vec![
+            Some(("resources/objects/ground/ground.obj".to_string(), None)),
+            // the texture comes from the objects list the hash was made from
+            Some((
+                "resources/objects/grass/grass.obj".to_string(),
+                Some("foliage.png".to_string())
+            )),
+            // in no list, found under the resources directory
+            Some(("resources/objects/gaz_tank/gaz_tank.obj".to_string(), None)),
+            None,
+        ]
+    );
+    assert_eq!(cache.loaded_models(), 0);
+}
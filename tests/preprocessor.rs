@@ -0,0 +1,91 @@
+use doppler::error::DopplerError;
+use doppler::preprocessor::{preprocess_file, LineOrigin};
+use doppler::shader::{ShaderError, ShaderStage};
+use std::fs;
+use std::path::PathBuf;
+
+fn shader_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("doppler_preprocessor_{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("common")).unwrap();
+    fs::write(
+        dir.join("common/lighting.glsl"),
+        "#include \"common/types.glsl\"\nstruct Light { Vec position; };\n",
+    )
+    .unwrap();
+    fs::write(dir.join("common/types.glsl"), "#define Vec vec3\n").unwrap();
+    fs::write(
+        dir.join("lit.fs"),
+        "#version 330 core\n#include \"common/lighting.glsl\"\n#include <common/types.glsl>\nuniform Light lights[NR_LIGHTS];\nvoid main() { broken }\n",
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn includes_are_resolved_once_and_defines_follow_version() {
+    let dir = shader_dir("includes");
+    let path = dir.join("lit.fs");
+    let defines = vec![("NR_LIGHTS".to_string(), "4".to_string())];
+    let result = preprocess_file(path.to_str().unwrap(), &defines).unwrap();
+
+    assert_eq!(
+        result.source,
+        "#version 330 core\n#define NR_LIGHTS 4\n#define Vec vec3\nstruct Light { Vec position; };\nuniform Light lights[NR_LIGHTS];\nvoid main() { broken }\n"
+    );
+    assert_eq!(result.files.len(), 3);
+    assert_eq!(
+        result.lines[1],
+        LineOrigin {
+            file: None,
+            line: 1
+        }
+    );
+    assert_eq!(
+        result.lines[2],
+        LineOrigin {
+            file: Some(2),
+            line: 1
+        }
+    );
+    assert_eq!(
+        result.lines[5],
+        LineOrigin {
+            file: Some(0),
+            line: 5
+        }
+    );
+}
+
+#[test]
+fn errors_point_at_original_files() {
+    let dir = shader_dir("errors");
+    let path = dir.join("lit.fs");
+    let result = preprocess_file(path.to_str().unwrap(), &[]).unwrap();
+    let log = "0:3(17): error: syntax error\n0:5(15): error: `broken' undeclared\n";
+    let e = ShaderError::from_preprocessed(ShaderStage::Fragment, log.into(), &result);
+
+    let lighting = dir.join("common/lighting.glsl").display().to_string();
+    let root = path.display().to_string();
+    assert_eq!(e.path, Some(root.clone()));
+    assert_eq!(e.lines[0].file, Some(lighting));
+    assert_eq!(e.lines[0].number, 2);
+    assert_eq!(e.lines[1].file, Some(root));
+    assert_eq!(e.lines[1].number, 5);
+    assert_eq!(e.lines[1].text, "void main() { broken }");
+}
+
+#[test]
+fn missing_include_reports_location() {
+    let dir = shader_dir("missing");
+    let path = dir.join("missing.fs");
+    fs::write(&path, "#version 330 core\n\n#include \"nope.glsl\"\n").unwrap();
+
+    match preprocess_file(path.to_str().unwrap(), &[]) {
+        Err(DopplerError::Preprocess { line, message, .. }) => {
+            assert_eq!(line, 3);
+            assert!(message.contains("nope.glsl"));
+        }
+        other => panic!("expected preprocess error, got {:?}", other.map(|_| ())),
+    }
+}
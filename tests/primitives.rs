@@ -0,0 +1,74 @@
+use cgmath::prelude::*;
+use doppler::mesh::primitives;
+use doppler::model::MeshData;
+
+fn shapes() -> Vec<(&'static str, MeshData)> {
+    vec![
+        ("cube", primitives::cube(2.0)),
+        ("plane", primitives::plane(2.0, 4)),
+        ("sphere", primitives::uv_sphere(10.0, 16, 8)),
+        ("cylinder", primitives::cylinder(1.0, 2.0, 12)),
+        ("capsule", primitives::capsule(0.5, 1.0, 12, 4)),
+        ("cone", primitives::cone(1.0, 2.0, 12)),
+        ("torus", primitives::torus(1.0, 0.25, 24, 12)),
+    ]
+}
+
+#[test]
+fn triangles_face_along_their_normals() {
+    for (name, mesh) in shapes() {
+        assert!(!mesh.indices.is_empty(), "{}", name);
+        assert_eq!(mesh.indices.len() % 3, 0, "{}", name);
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [
+                &mesh.vertices[triangle[0] as usize],
+                &mesh.vertices[triangle[1] as usize],
+                &mesh.vertices[triangle[2] as usize],
+            ];
+            let face = (b.position - a.position).cross(c.position - a.position);
+            assert!(face.magnitude() > 1e-6, "{}: degenerate triangle", name);
+            let normals = a.normal + b.normal + c.normal;
+            assert!(face.dot(normals) > 0.0, "{}: {:?}", name, triangle);
+        }
+        for vertex in &mesh.vertices {
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-4, "{}", name);
+            assert!(vertex.normal.dot(vertex.tangent).abs() < 1e-4, "{}", name);
+            let uv = vertex.text_coords;
+            assert!((0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y));
+        }
+    }
+}
+
+#[test]
+fn shapes_have_the_requested_size() {
+    let sphere = primitives::uv_sphere(10.0, 16, 8);
+    for vertex in &sphere.vertices {
+        assert!((vertex.position.magnitude() - 10.0).abs() < 1e-3);
+    }
+
+    let capsule = primitives::capsule(0.5, 1.0, 12, 4);
+    let top = capsule
+        .vertices
+        .iter()
+        .map(|v| v.position.y)
+        .fold(f32::MIN, f32::max);
+    assert!((top - 1.0).abs() < 1e-5);
+
+    let cube = primitives::cube(2.0);
+    assert_eq!(cube.vertices.len(), 24);
+    assert!(cube.vertices.iter().all(|v| v.position.x.abs() == 1.0
+        || v.position.y.abs() == 1.0
+        || v.position.z.abs() == 1.0));
+}
+
+#[test]
+fn plane_is_subdivided_into_quads() {
+    let plane = primitives::plane(4.0, 3);
+    assert_eq!(plane.vertices.len(), 16);
+    assert_eq!(plane.indices.len(), 3 * 3 * 6);
+    assert!(plane.vertices.iter().all(|v| v.position.y == 0.0));
+    assert!(plane
+        .vertices
+        .iter()
+        .all(|v| v.position.x.abs() <= 2.0 && v.position.z.abs() <= 2.0));
+}
@@ -0,0 +1,29 @@
+use doppler::mesh::RingCursor;
+
+#[test]
+fn reserves_consecutive_ranges_until_wrapping() {
+    let mut cursor = RingCursor::new(10);
+
+    let first = cursor.reserve(4);
+    assert_eq!(first.range, 0..4);
+    assert!(!first.orphan);
+    assert_eq!(cursor.reserve(6).range, 4..10);
+
+    // does not fit before the end: start over in a fresh buffer
+    let wrapped = cursor.reserve(3);
+    assert_eq!(wrapped.range, 0..3);
+    assert!(wrapped.orphan);
+    assert_eq!(cursor.capacity, 10);
+}
+
+#[test]
+fn grows_when_a_write_is_larger_than_the_buffer() {
+    let mut cursor = RingCursor::new(8);
+    cursor.reserve(5);
+
+    let write = cursor.reserve(12);
+    assert_eq!(write.range, 0..12);
+    assert!(write.orphan);
+    assert_eq!(cursor.capacity, 16);
+    assert_eq!(cursor.position, 12);
+}
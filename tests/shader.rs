@@ -0,0 +1,113 @@
+use doppler::shader::{ShaderBuilder, ShaderError, ShaderStage, SourceLine};
+
+const SOURCE: &str = "#version 330 core
+out vec4 FragColor;
+
+void main()
+{
+    FragColor = vec4(1.0) * missing;
+}";
+
+#[test]
+fn mesa_log_picks_offending_lines() {
+    let log = "0:6(30): error: `missing' undeclared\n0:6(17): error: operands to arithmetic operators must be numeric\n";
+    let e = ShaderError::new(ShaderStage::Fragment, log.into(), Some(SOURCE));
+
+    assert_eq!(
+        e.lines,
+        vec![SourceLine {
+            file: None,
+            number: 6,
+            text: "    FragColor = vec4(1.0) * missing;".into(),
+        }]
+    );
+}
+
+#[test]
+fn nvidia_and_amd_logs_are_understood() {
+    let nvidia = "0(2) : error C0000: syntax error\n0(7) : error C1503: undefined variable";
+    let e = ShaderError::new(ShaderStage::Fragment, nvidia.into(), Some(SOURCE));
+    assert_eq!(
+        e.lines.iter().map(|line| line.number).collect::<Vec<_>>(),
+        vec![2, 7]
+    );
+
+    let amd = "ERROR: 0:4: '{' : syntax error";
+    let e = ShaderError::new(ShaderStage::Vertex, amd.into(), Some(SOURCE));
+    assert_eq!(e.lines[0].number, 4);
+    assert_eq!(e.lines[0].text, "void main()");
+}
+
+#[test]
+fn display_contains_stage_log_and_numbered_lines() {
+    let mut e = ShaderError::new(
+        ShaderStage::Fragment,
+        "0:6(30): error: `missing' undeclared\n".into(),
+        Some(SOURCE),
+    );
+    e.path = Some("resources/shaders/broken.fs".into());
+
+    assert_eq!(
+        e.to_string(),
+        "fragment shader failed (resources/shaders/broken.fs):\n\
+         0:6(30): error: `missing' undeclared\n\
+         \x20   6 |     FragColor = vec4(1.0) * missing;"
+    );
+}
+
+#[test]
+fn uniform_types_match_glsl_types() {
+    use doppler::gl;
+    use doppler::math::{Matrix4, Vector3};
+    use doppler::uniform::Uniform;
+
+    assert!(i32::accepts(gl::SAMPLER_2D));
+    assert!(bool::accepts(gl::BOOL));
+    assert!(!f32::accepts(gl::INT));
+    assert!(<&Vector3<f32>>::accepts(gl::FLOAT_VEC3));
+    assert!(!Matrix4::<f32>::accepts(gl::FLOAT_MAT3));
+}
+
+#[test]
+fn builder_rejects_invalid_stage_combinations() {
+    let problem = |builder: ShaderBuilder| builder.validate().unwrap_err().log;
+
+    assert_eq!(problem(ShaderBuilder::new()), "no shader stages");
+    assert_eq!(
+        problem(ShaderBuilder::new().vertex(SOURCE).compute(SOURCE)),
+        "compute shaders cannot be combined with other stages"
+    );
+    assert_eq!(
+        problem(ShaderBuilder::new().fragment(SOURCE)),
+        "missing vertex stage"
+    );
+    assert_eq!(
+        problem(ShaderBuilder::new().vertex(SOURCE).tess_control(SOURCE)),
+        "tessellation control stage without tessellation evaluation stage"
+    );
+
+    let e = ShaderBuilder::new()
+        .fragment_file("resources/shaders/skybox.fs")
+        .validate()
+        .unwrap_err();
+    assert_eq!(e.stage, ShaderStage::Program);
+    assert_eq!(e.path.as_deref(), Some("resources/shaders/skybox.fs"));
+}
+
+#[test]
+fn builder_accepts_compute_and_tessellation_programs() {
+    assert!(ShaderBuilder::new().compute(SOURCE).validate().is_ok());
+    assert!(ShaderBuilder::new()
+        .vertex(SOURCE)
+        .tess_control(SOURCE)
+        .tess_evaluation(SOURCE)
+        .fragment(SOURCE)
+        .validate()
+        .is_ok());
+    // setting a stage twice replaces it
+    assert!(ShaderBuilder::new()
+        .compute(SOURCE)
+        .compute_file("particles.comp")
+        .validate()
+        .is_ok());
+}
@@ -0,0 +1,54 @@
+use cgmath::{vec3, Matrix4, SquareMatrix};
+use doppler::light::{DirectionalLight, PointLight};
+use doppler::uniform_buffer::{camera_block, lights_block, Std140};
+
+fn floats(block: &Std140) -> Vec<f32> {
+    block
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[test]
+fn scalars_pack_after_vec3_and_vec3_aligns_to_16() {
+    let mut block = Std140::new();
+    block.float(1.0).vec3(vec3(2.0, 3.0, 4.0)).float(5.0);
+
+    assert_eq!(block.len(), 32);
+    assert_eq!(floats(&block), vec![1.0, 0.0, 0.0, 0.0, 2.0, 3.0, 4.0, 5.0]);
+}
+
+#[test]
+fn camera_block_layout() {
+    let block = camera_block(
+        &Matrix4::identity(),
+        &Matrix4::identity(),
+        vec3(1.0, 2.0, 3.0),
+    );
+
+    assert_eq!(block.len(), 16 * 4 + 16 * 4 + 12);
+    assert_eq!(&floats(&block)[32..35], &[1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn lights_block_matches_glsl_struct_layout() {
+    let point = PointLight {
+        pos: vec3(1.0, 2.0, 3.0),
+        constant: 4.0,
+        linear: 5.0,
+        quadratic: 6.0,
+        ambient: vec3(7.0, 7.0, 7.0),
+        ..PointLight::default()
+    };
+    let block = lights_block(&DirectionalLight::default(), &[point, point]);
+    let values = floats(&block);
+
+    // DirLight is four padded vec3, every PointLight takes 80 bytes
+    assert_eq!(block.len(), 64 + 2 * 80);
+    for base in &[16, 36] {
+        assert_eq!(&values[*base..*base + 3], &[1.0, 2.0, 3.0]);
+        assert_eq!(&values[*base + 3..*base + 6], &[4.0, 5.0, 6.0]);
+        assert_eq!(values[*base + 8], 7.0);
+    }
+}
@@ -0,0 +1,79 @@
+use cgmath::vec4;
+use doppler::mesh::{ComponentType, Vertex, VertexAttribute, VertexFormat, VertexLayout};
+
+/// 2D sprite vertex: position, texture coordinates and a normalized RGBA color.
+#[repr(C)]
+#[derive(Clone)]
+#[allow(dead_code)]
+struct SpriteVertex {
+    position: [f32; 2],
+    text_coords: [f32; 2],
+    color: [u8; 4],
+}
+
+impl VertexFormat for SpriteVertex {
+    fn layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<SpriteVertex>())
+            .with(VertexAttribute::new(
+                "position",
+                0,
+                ComponentType::F32,
+                2,
+                0,
+            ))
+            .with(VertexAttribute::new(
+                "text_coords",
+                2,
+                ComponentType::F32,
+                2,
+                8,
+            ))
+            .with(VertexAttribute::new("color", 5, ComponentType::U8, 4, 16).normalized())
+    }
+}
+
+#[test]
+fn default_vertex_layout_matches_the_struct() {
+    let layout = Vertex::layout();
+
+    assert_eq!(layout.stride, 72);
+    assert_eq!(layout.validate(), Ok(()));
+    let locations: Vec<u32> = layout.attributes.iter().map(|a| a.location).collect();
+    assert_eq!(locations, vec![0, 1, 2, 3, 4, 5]);
+    let bitangent = layout.attribute("bitangent").unwrap();
+    assert_eq!((bitangent.offset, bitangent.size()), (44, 12));
+    let color = layout.attribute("color").unwrap();
+    assert_eq!((color.offset, color.size()), (56, 16));
+}
+
+#[test]
+fn vertices_without_colors_are_white() {
+    assert_eq!(Vertex::default().color, vec4(1.0, 1.0, 1.0, 1.0));
+}
+
+#[test]
+fn custom_layouts_can_be_smaller() {
+    let layout = SpriteVertex::layout();
+
+    assert_eq!(layout.stride, 20);
+    assert_eq!(layout.validate(), Ok(()));
+    assert!(layout.attribute("color").unwrap().normalized);
+}
+
+#[test]
+fn invalid_layouts_are_reported() {
+    let overlapping = VertexLayout::new(24)
+        .with(VertexAttribute::new("a", 0, ComponentType::F32, 3, 0))
+        .with(VertexAttribute::new("b", 0, ComponentType::F32, 3, 12));
+    assert_eq!(
+        overlapping.validate(),
+        Err("attributes a and b share location 0".to_string())
+    );
+
+    let too_long =
+        VertexLayout::new(8).with(VertexAttribute::new("a", 0, ComponentType::F32, 3, 0));
+    assert_eq!(
+        too_long.validate(),
+        Err("attribute a ends at byte 12, after the vertex stride 8".to_string())
+    );
+}